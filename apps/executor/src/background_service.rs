@@ -1,18 +1,27 @@
 use log::info;
+use uuid::Uuid;
 
 use crate::queue_handler::{
-    handle_awaiting_queue, handle_failure_queue, handle_processing_queue, handle_success_queue,
+    handle_awaiting_queue, handle_callback_queue, handle_failure_queue, handle_heartbeat,
+    handle_in_flight_reaper, handle_processing_queue, handle_retrying_queue, handle_success_queue,
 };
 use cosmic::app_state::AppState;
 
 pub(crate) fn create_background_service(app: &AppState, name: String, features_per_worker: i8) {
     info!("preparing thread: {}", name.clone());
 
-    handle_awaiting_queue(app, name.clone());
-    handle_success_queue(app, name.clone());
-    handle_failure_queue(app, name.clone());
+    // unique per run, in-flight lists of a previous run are reaped once its heartbeat expires
+    let worker_id = format!("{}:{}", name, Uuid::new_v4());
 
-    for _loop_index in 0..features_per_worker {
-        handle_processing_queue(&app.clone(), name.clone());
+    handle_heartbeat(app, name.clone(), worker_id.clone());
+    handle_in_flight_reaper(app, name.clone());
+    handle_awaiting_queue(app, name.clone(), worker_id.clone());
+    handle_success_queue(app, name.clone(), worker_id.clone());
+    handle_failure_queue(app, name.clone(), worker_id.clone());
+    handle_callback_queue(app, name.clone(), worker_id.clone());
+    handle_retrying_queue(app, name.clone());
+
+    for loop_index in 0..features_per_worker {
+        handle_processing_queue(&app.clone(), name.clone(), worker_id.clone(), loop_index);
     }
 }
@@ -31,7 +31,7 @@ async fn main() -> std::io::Result<()> {
 
     info!("starting server at http://localhost:{}", port);
 
-    let app_state = make_app_state().await;
+    let app_state = make_app_state().await?;
     let worker_count = Arc::new(Mutex::new(0));
 
     HttpServer::new(move || {
@@ -2,27 +2,96 @@ use std::time::Duration;
 
 use actix_web::rt::{spawn, time};
 use log::{error, info};
-use redis::Commands;
+use reqwest::Client;
 
 use cosmic::app_state::AppState;
+use cosmic::enums::app_message::AppMessage;
+use cosmic::helpers::time::current_timestamp;
 use cosmic::models::mail::{
-    MailFailureResponse, MailQueueablePayload, MailSaved, MailSuccessResponse,
+    InFlightQueue, MailCallbackPayload, MailFailureResponse, MailPriority, MailQueueablePayload,
+    MailSaved, MailStatus, MailSuccessResponse, ThrottleDecision,
 };
+use cosmic::models::webhook_delivery::{WebhookDeliveryStatus, WebhookEvent};
+use cosmic::results::AppResult;
 use cosmic::services::mail_service::MailService;
+use cosmic::services::queue_service::QueueService;
+use cosmic::services::rate_limit_service::RateLimitService;
+use cosmic::services::webhook_service::WebhookService;
 
-use crate::redis_error_handler::handle_redis_error;
+use crate::redis_error_handler::{handle_pop_error, handle_redis_error};
 
-pub(crate) fn handle_awaiting_queue(app: &AppState, thread_name: String) {
+/// Keeps trying until the consumer's in-flight list is registered, nothing is popped before that
+async fn register_consumer(
+    app: &AppState,
+    queue: &str,
+    worker_id: &str,
+    name: String,
+    thread_name: String,
+) -> InFlightQueue {
+    let mut interval = time::interval(Duration::from_secs(1));
+    let consumer = format!("{}:{}", worker_id, name);
+    loop {
+        match QueueService.register(app, queue, worker_id, &consumer) {
+            Ok(in_flight) => return in_flight,
+            Err(err) => {
+                handle_redis_error(err, thread_name.clone(), "register_consumer");
+                interval.tick().await;
+            }
+        };
+    }
+}
+
+/// Puts back an item whose handling failed midway, it is only acked once every step succeeded
+fn requeue(
+    app: &AppState,
+    in_flight: &InFlightQueue,
+    item: &str,
+    err: AppMessage,
+    thread_name: String,
+    task_name: &str,
+) {
+    requeue_as(app, in_flight, item, item, err, thread_name, task_name)
+}
+
+/// Same as `requeue`, with the item updated to what was handled so far
+fn requeue_as(
+    app: &AppState,
+    in_flight: &InFlightQueue,
+    item: &str,
+    replacement: &str,
+    err: AppMessage,
+    thread_name: String,
+    task_name: &str,
+) {
+    error!(
+        "[{}][{}] failed to handle item, re-queueing: {:?}",
+        thread_name, task_name, err
+    );
+    if let Err(err) = QueueService.replace(app, in_flight, item, replacement) {
+        handle_redis_error(err, thread_name, task_name);
+    }
+}
+
+pub(crate) fn handle_awaiting_queue(app: &AppState, thread_name: String, worker_id: String) {
     let app = app.clone();
     spawn(async move {
         let mut interval = time::interval(Duration::from_millis(200));
+        let in_flight = register_consumer(
+            &app,
+            &app.redis_queues.awaiting,
+            &worker_id,
+            String::from("awaiting"),
+            thread_name.clone(),
+        )
+        .await;
+        let scheduled = MailStatus::Scheduled.to_string();
+        let suppressed = MailStatus::Suppressed.to_string();
         loop {
-            let mut redis = app.redis.clone();
-            let popped = redis.rpop::<&str, String>(&*app.redis_queues.awaiting, None);
+            let popped = QueueService.pop(&app, &in_flight).await;
             match popped {
-                Ok(item) => {
+                Ok(Some(item)) => {
                     let payload_res = serde_json::from_str::<MailQueueablePayload>(item.as_str());
-                    match payload_res {
+                    let handled: AppResult<()> = match payload_res {
                         Ok(mut payload) => {
                             let subject = payload.subject.clone();
 
@@ -31,18 +100,74 @@ pub(crate) fn handle_awaiting_queue(app: &AppState, thread_name: String) {
                             payload.from =
                                 Option::from(payload.from.unwrap_or_else(|| app.mail_from.clone()));
 
+                            let rendered = MailService.apply_template(&app, &mut payload);
+
                             match MailService.create(app.database(), payload) {
-                                Ok(mail) => {
-                                    let _ = MailService.push_to_processing_queue(&app, mail);
-                                }
-                                Err(err) => {
-                                    error!(
-                                        "[{}][handle_awaiting_queue] db error: {:?}",
-                                        thread_name.clone(),
-                                        err
-                                    );
-                                }
-                            };
+                                Ok(mail) => match rendered {
+                                    Ok(_) if mail.mail.status == suppressed => {
+                                        let _ = WebhookService.dispatch(
+                                            &app,
+                                            &mail.mail,
+                                            WebhookEvent::Suppressed,
+                                            None,
+                                        );
+                                        Ok(())
+                                    }
+                                    Ok(_) if mail.mail.status == scheduled => {
+                                        let created = mail.mail.clone();
+                                        MailService
+                                            .push_to_scheduled_queue(&app, mail)
+                                            .map(|_| {
+                                                let _ = WebhookService.dispatch(
+                                                    &app,
+                                                    &created,
+                                                    WebhookEvent::Scheduled,
+                                                    None,
+                                                );
+                                            })
+                                            .map_err(AppMessage::from)
+                                    }
+                                    Ok(_) => {
+                                        let created = mail.mail.clone();
+                                        MailService
+                                            .push_to_processing_queue(&app, mail)
+                                            .map(|_| {
+                                                let _ = WebhookService.dispatch(
+                                                    &app,
+                                                    &created,
+                                                    WebhookEvent::Queued,
+                                                    None,
+                                                );
+                                            })
+                                            .map_err(AppMessage::from)
+                                    }
+                                    Err(err) => {
+                                        let error = err.to_string();
+                                        error!(
+                                            "[{}] failed to render mail #{}: {}",
+                                            thread_name.clone(),
+                                            mail.mail.mail_id,
+                                            error
+                                        );
+
+                                        MailService
+                                            .mark_as_render_failure(
+                                                app.database(),
+                                                mail,
+                                                error.clone(),
+                                            )
+                                            .map(|mail| {
+                                                let _ = WebhookService.dispatch(
+                                                    &app,
+                                                    &mail,
+                                                    WebhookEvent::Failed,
+                                                    Some(error),
+                                                );
+                                            })
+                                    }
+                                },
+                                Err(err) => Err(err),
+                            }
                         }
                         Err(err) => {
                             error!(
@@ -50,11 +175,23 @@ pub(crate) fn handle_awaiting_queue(app: &AppState, thread_name: String) {
                                 thread_name.clone(),
                                 err
                             );
+                            Ok(())
                         }
                     };
+
+                    // a mail persisted already is picked up from the database when handled again
+                    if let Err(err) = handled {
+                        let task = "handle_awaiting_queue";
+                        requeue(&app, &in_flight, &item, err, thread_name.clone(), task);
+                        interval.tick().await;
+                        continue;
+                    }
+
+                    let _ = QueueService.ack(&app, &in_flight, &item);
                 }
+                Ok(None) => {}
                 Err(err) => {
-                    handle_redis_error(err, thread_name.clone(), "handle_awaiting_queue");
+                    handle_pop_error(err, thread_name.clone(), "handle_awaiting_queue");
                     interval.tick().await;
                 }
             };
@@ -62,21 +199,106 @@ pub(crate) fn handle_awaiting_queue(app: &AppState, thread_name: String) {
     });
 }
 
-pub(crate) fn handle_processing_queue(app: &AppState, thread_name: String) {
+pub(crate) fn handle_processing_queue(
+    app: &AppState,
+    thread_name: String,
+    worker_id: String,
+    index: i8,
+) {
     let app = app.clone();
     spawn(async move {
         let mut interval = time::interval(Duration::from_millis(200));
+        let mut in_flights = vec![];
+        for priority in [MailPriority::High, MailPriority::Normal, MailPriority::Bulk] {
+            let in_flight = register_consumer(
+                &app,
+                &app.redis_queues.processing_of(priority),
+                &worker_id,
+                format!("processing-{}-{}", index, priority),
+                thread_name.clone(),
+            )
+            .await;
+            in_flights.push((priority, in_flight));
+        }
+
+        let scheduled = MailStatus::Scheduled.to_string();
         loop {
-            let mut redis = app.redis.clone();
-            let popped = redis.rpop::<&str, String>(&*app.redis_queues.processing, None);
+            let queues: Vec<&InFlightQueue> = MailService
+                .processing_order(&app)
+                .iter()
+                .filter_map(|priority| {
+                    in_flights
+                        .iter()
+                        .find(|(p, _)| p == priority)
+                        .map(|(_, in_flight)| in_flight)
+                })
+                .collect();
+
+            let popped = QueueService.pop_first(&app, &queues).await;
             match popped {
-                Ok(item) => {
+                Ok(Some((position, item))) => {
+                    let in_flight = queues[position];
                     let payload_res = serde_json::from_str::<MailSaved>(item.as_str());
-                    match payload_res {
+                    let handled: AppResult<()> = match payload_res {
                         Ok(saved) => {
-                            let subject = saved.mail.subject.clone();
-                            info!("[{}] processing: {}", thread_name.clone(), subject);
-                            MailService.send(&app, thread_name.clone(), saved).await;
+                            // the mail may have been cancelled or rescheduled while queued,
+                            // only a mail that is still to be sent takes up rate limits
+                            let was_scheduled = saved.mail.status == scheduled;
+                            match MailService.claim_for_sending(app.database(), saved) {
+                                Ok(Some(saved)) => {
+                                    if was_scheduled {
+                                        let _ = WebhookService.dispatch(
+                                            &app,
+                                            &saved.mail,
+                                            WebhookEvent::Queued,
+                                            None,
+                                        );
+                                    }
+
+                                    let acquired =
+                                        match RateLimitService.throttle(&app, &saved).await {
+                                            Ok(ThrottleDecision::Permitted(domains)) => domains,
+                                            Ok(ThrottleDecision::Deferred) => {
+                                                info!(
+                                                    "[{}] throttled mail #{}",
+                                                    thread_name.clone(),
+                                                    saved.mail.mail_id
+                                                );
+                                                let _ = QueueService.ack(&app, in_flight, &item);
+                                                continue;
+                                            }
+                                            // never sent unthrottled, it is put back as claimed
+                                            // since a scheduled mail is no longer scheduled
+                                            Err(err) => {
+                                                let claimed =
+                                                    serde_json::to_string(&saved).unwrap();
+                                                requeue_as(
+                                                    &app,
+                                                    in_flight,
+                                                    &item,
+                                                    &claimed,
+                                                    err,
+                                                    thread_name.clone(),
+                                                    "handle_processing_queue",
+                                                );
+                                                interval.tick().await;
+                                                continue;
+                                            }
+                                        };
+
+                                    let mail_id = saved.mail.mail_id;
+                                    let subject = saved.mail.subject.clone();
+                                    info!("[{}] processing: {}", thread_name.clone(), subject);
+                                    MailService.send(&app, thread_name.clone(), saved).await;
+                                    RateLimitService.release(&app, mail_id, &acquired);
+                                    Ok(())
+                                }
+                                Ok(None) => {
+                                    info!("[{}] skipping mail", thread_name.clone());
+                                    Ok(())
+                                }
+                                Err(err) => Err(err),
+                            }
                         }
                         Err(err) => {
                             error!(
@@ -84,11 +306,22 @@ pub(crate) fn handle_processing_queue(app: &AppState, thread_name: String) {
                                 thread_name.clone(),
                                 err
                             );
+                            Ok(())
                         }
                     };
+
+                    if let Err(err) = handled {
+                        let task = "handle_processing_queue";
+                        requeue(&app, in_flight, &item, err, thread_name.clone(), task);
+                        interval.tick().await;
+                        continue;
+                    }
+
+                    let _ = QueueService.ack(&app, in_flight, &item);
                 }
+                Ok(None) => {}
                 Err(err) => {
-                    handle_redis_error(err, thread_name.clone(), "handle_processing_queue");
+                    handle_pop_error(err, thread_name.clone(), "handle_processing_queue");
                     interval.tick().await;
                 }
             };
@@ -96,17 +329,24 @@ pub(crate) fn handle_processing_queue(app: &AppState, thread_name: String) {
     });
 }
 
-pub(crate) fn handle_success_queue(app: &AppState, thread_name: String) {
+pub(crate) fn handle_success_queue(app: &AppState, thread_name: String, worker_id: String) {
     let app = app.clone();
     spawn(async move {
         let mut interval = time::interval(Duration::from_millis(200));
+        let in_flight = register_consumer(
+            &app,
+            &app.redis_queues.success,
+            &worker_id,
+            String::from("success"),
+            thread_name.clone(),
+        )
+        .await;
         loop {
-            let mut redis = app.redis.clone();
-            let popped = redis.rpop::<&str, String>(&*app.redis_queues.success, None);
+            let popped = QueueService.pop(&app, &in_flight).await;
             match popped {
-                Ok(item) => {
+                Ok(Some(item)) => {
                     let payload_res = serde_json::from_str::<MailSuccessResponse>(item.as_str());
-                    match payload_res {
+                    let handled: AppResult<()> = match payload_res {
                         Ok(response) => {
                             info!(
                                 "[{}] marking as success: {}",
@@ -114,7 +354,16 @@ pub(crate) fn handle_success_queue(app: &AppState, thread_name: String) {
                                 response.saved_mail.mail.subject.clone()
                             );
 
-                            let _ = MailService.mark_as_success(app.database(), response);
+                            MailService
+                                .mark_as_success(app.database(), response)
+                                .map(|mail| {
+                                    let _ = WebhookService.dispatch(
+                                        &app,
+                                        &mail,
+                                        WebhookEvent::Sent,
+                                        None,
+                                    );
+                                })
                         }
                         Err(err) => {
                             error!(
@@ -122,11 +371,22 @@ pub(crate) fn handle_success_queue(app: &AppState, thread_name: String) {
                                 thread_name.clone(),
                                 err
                             );
+                            Ok(())
                         }
                     };
+
+                    if let Err(err) = handled {
+                        let task = "handle_success_queue";
+                        requeue(&app, &in_flight, &item, err, thread_name.clone(), task);
+                        interval.tick().await;
+                        continue;
+                    }
+
+                    let _ = QueueService.ack(&app, &in_flight, &item);
                 }
+                Ok(None) => {}
                 Err(err) => {
-                    handle_redis_error(err, thread_name.clone(), "handle_success_queue");
+                    handle_pop_error(err, thread_name.clone(), "handle_success_queue");
                     interval.tick().await;
                 }
             };
@@ -134,17 +394,24 @@ pub(crate) fn handle_success_queue(app: &AppState, thread_name: String) {
     });
 }
 
-pub(crate) fn handle_failure_queue(app: &AppState, thread_name: String) {
+pub(crate) fn handle_failure_queue(app: &AppState, thread_name: String, worker_id: String) {
     let app = app.clone();
     spawn(async move {
         let mut interval = time::interval(Duration::from_millis(200));
+        let in_flight = register_consumer(
+            &app,
+            &app.redis_queues.failure,
+            &worker_id,
+            String::from("failure"),
+            thread_name.clone(),
+        )
+        .await;
         loop {
-            let mut redis = app.redis.clone();
-            let popped = redis.rpop::<&str, String>(&*app.redis_queues.failure, None);
+            let popped = QueueService.pop(&app, &in_flight).await;
             match popped {
-                Ok(item) => {
+                Ok(Some(item)) => {
                     let payload_res = serde_json::from_str::<MailFailureResponse>(item.as_str());
-                    match payload_res {
+                    let handled: AppResult<()> = match payload_res {
                         Ok(response) => {
                             let mut saved = response.saved_mail.clone();
                             info!(
@@ -153,23 +420,42 @@ pub(crate) fn handle_failure_queue(app: &AppState, thread_name: String) {
                                 saved.mail.subject.clone()
                             );
 
+                            let error_message = Some(response.error_message.clone());
                             let trials = saved.mail.trials + 1;
-                            match trials < app.max_retrials {
+                            match trials < app.max_retrials && !response.smtp.permanent {
                                 true => {
                                     info!("retrying: {}", trials);
 
-                                    let mail =
-                                        MailService.mark_as_retrying(app.database(), response);
+                                    let delay = MailService.retry_delay(&app, trials);
+                                    let next_retrial_at = current_timestamp() + delay;
+                                    MailService
+                                        .mark_as_retrying(app.database(), response, next_retrial_at)
+                                        .and_then(|mail| {
+                                            let _ = WebhookService.dispatch(
+                                                &app,
+                                                &mail,
+                                                WebhookEvent::Retrying,
+                                                error_message,
+                                            );
 
-                                    if let Ok(mail) = mail {
-                                        saved.mail = mail;
-                                        let _ = MailService.push_to_processing_queue(&app, saved);
-                                    }
-                                }
-                                false => {
-                                    let _ = MailService.mark_as_failure(app.database(), response);
+                                            saved.mail = mail;
+                                            MailService
+                                                .push_to_retrying_queue(&app, saved)
+                                                .map(|_| ())
+                                                .map_err(AppMessage::from)
+                                        })
                                 }
-                            };
+                                false => MailService.mark_as_failure(app.database(), response).map(
+                                    |mail| {
+                                        let _ = WebhookService.dispatch(
+                                            &app,
+                                            &mail,
+                                            WebhookEvent::Failed,
+                                            error_message,
+                                        );
+                                    },
+                                ),
+                            }
                         }
                         Err(err) => {
                             error!(
@@ -177,14 +463,176 @@ pub(crate) fn handle_failure_queue(app: &AppState, thread_name: String) {
                                 thread_name.clone(),
                                 err
                             );
+                            Ok(())
+                        }
+                    };
+
+                    if let Err(err) = handled {
+                        let task = "handle_failure_queue";
+                        requeue(&app, &in_flight, &item, err, thread_name.clone(), task);
+                        interval.tick().await;
+                        continue;
+                    }
+
+                    let _ = QueueService.ack(&app, &in_flight, &item);
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    handle_pop_error(err, thread_name.clone(), "handle_failure_queue");
+                    interval.tick().await;
+                }
+            };
+        }
+    });
+}
+
+pub(crate) fn handle_callback_queue(app: &AppState, thread_name: String, worker_id: String) {
+    let app = app.clone();
+    spawn(async move {
+        let mut interval = time::interval(Duration::from_millis(200));
+        let in_flight = register_consumer(
+            &app,
+            &app.redis_queues.callback,
+            &worker_id,
+            String::from("callback"),
+            thread_name.clone(),
+        )
+        .await;
+        let client = Client::builder()
+            .timeout(Duration::from_secs(app.webhook_timeout))
+            .build()
+            .unwrap();
+
+        let retrying = WebhookDeliveryStatus::Retrying.to_string();
+        loop {
+            let popped = QueueService.pop(&app, &in_flight).await;
+            match popped {
+                Ok(Some(item)) => {
+                    let payload_res = serde_json::from_str::<MailCallbackPayload>(item.as_str());
+                    let mut replacement = item.clone();
+                    let handled: AppResult<()> = match payload_res {
+                        Ok(mut payload) => {
+                            info!(
+                                "[{}] delivering webhook: {} -> {}",
+                                thread_name.clone(),
+                                payload.event.mail_id,
+                                payload.event.event
+                            );
+
+                            match WebhookService.deliver(&app, &client, &payload).await {
+                                Ok(Some(delivery)) if delivery.status == retrying => {
+                                    // handled again as the delivery the attempt was recorded on
+                                    payload.webhook_delivery_id =
+                                        Some(delivery.webhook_delivery_id);
+                                    replacement = serde_json::to_string(&payload).unwrap();
+
+                                    MailService
+                                        .push_to_delayed_queue(
+                                            &app,
+                                            app.redis_queues.callback.clone(),
+                                            payload,
+                                            delivery.next_attempt_at.unwrap(),
+                                        )
+                                        .map(|_| ())
+                                        .map_err(AppMessage::from)
+                                }
+                                Ok(_) => Ok(()),
+                                Err(err) => Err(err),
+                            }
+                        }
+                        Err(err) => {
+                            error!(
+                                "[{}] error decoding callback: {:?}",
+                                thread_name.clone(),
+                                err
+                            );
+                            Ok(())
                         }
                     };
+
+                    if let Err(err) = handled {
+                        let task = "handle_callback_queue";
+                        let (thread, replacement) = (thread_name.clone(), &replacement);
+                        requeue_as(&app, &in_flight, &item, replacement, err, thread, task);
+                        interval.tick().await;
+                        continue;
+                    }
+
+                    let _ = QueueService.ack(&app, &in_flight, &item);
                 }
+                Ok(None) => {}
                 Err(err) => {
-                    handle_redis_error(err, thread_name.clone(), "handle_failure_queue");
+                    handle_pop_error(err, thread_name.clone(), "handle_callback_queue");
                     interval.tick().await;
                 }
             };
         }
     });
 }
+
+pub(crate) fn handle_retrying_queue(app: &AppState, thread_name: String) {
+    let app = app.clone();
+    spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            match MailService.release_due_items(&app, 100) {
+                Ok(0) => {}
+                Ok(released) => {
+                    info!(
+                        "[{}] released {} due item(s)",
+                        thread_name.clone(),
+                        released
+                    );
+
+                    // wakes processing workers waiting on the signal list
+                    if let Err(err) = QueueService.signal(&app) {
+                        handle_redis_error(err, thread_name.clone(), "handle_retrying_queue");
+                    }
+                }
+                Err(err) => {
+                    handle_redis_error(err, thread_name.clone(), "handle_retrying_queue");
+                }
+            };
+        }
+    });
+}
+
+pub(crate) fn handle_heartbeat(app: &AppState, thread_name: String, worker_id: String) {
+    let app = app.clone();
+    spawn(async move {
+        let period = (app.worker_heartbeat_ttl / 3).max(1);
+        let mut interval = time::interval(Duration::from_secs(period));
+        loop {
+            interval.tick().await;
+            if let Err(err) = QueueService.heartbeat(&app, &worker_id) {
+                handle_redis_error(err, thread_name.clone(), "handle_heartbeat");
+            }
+        }
+    });
+}
+
+/// Runs on startup and then periodically,
+/// re-queues whatever workers that stopped sending heartbeats left in flight
+pub(crate) fn handle_in_flight_reaper(app: &AppState, thread_name: String) {
+    let app = app.clone();
+    spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(app.worker_heartbeat_ttl.max(1)));
+        loop {
+            interval.tick().await;
+            match QueueService.reap(&app) {
+                Ok(0) => {}
+                Ok(requeued) => {
+                    info!(
+                        "[{}] re-queued {} in-flight item(s)",
+                        thread_name.clone(),
+                        requeued
+                    );
+                }
+                Err(err) => {
+                    handle_redis_error(err, thread_name.clone(), "handle_in_flight_reaper");
+                }
+            };
+        }
+    });
+}
@@ -1,8 +1,17 @@
 use log::error;
 use redis::RedisError;
 
+use cosmic::enums::app_message::AppMessage;
+
 pub(crate) fn handle_redis_error(err: RedisError, thread_name: String, task_name: &str) {
     if err.is_io_error() {
         error!("[{}][{}] redis error: {:?}", thread_name, task_name, err);
     }
 }
+
+pub(crate) fn handle_pop_error(err: AppMessage, thread_name: String, task_name: &str) {
+    match err {
+        AppMessage::RedisError(err) => handle_redis_error(err, thread_name, task_name),
+        err => error!("[{}][{}] redis error: {:?}", thread_name, task_name, err),
+    }
+}
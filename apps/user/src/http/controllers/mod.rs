@@ -1,9 +1,14 @@
 use cosmic::http::controllers::application_controller::application_controller;
 use cosmic::http::controllers::auth_controller::auth_controller;
+use cosmic::http::controllers::bounce_controller::bounce_controller;
+use cosmic::http::controllers::mail_controller::mail_controller;
 use cosmic::http::controllers::main_controller_guest::main_controller_guest;
 use cosmic::http::controllers::misc_controller::misc_controller;
 use cosmic::http::controllers::profile_controller::profile_controller;
 use cosmic::http::controllers::setting_controller::setting_controller;
+use cosmic::http::controllers::smtp_relay_controller::smtp_relay_controller;
+use cosmic::http::controllers::suppression_controller::suppression_controller;
+use cosmic::http::controllers::template_controller::template_controller;
 use cosmic::http::kernel::{Controller, Route};
 use cosmic::http::middlewares::auth_middleware::AuthMiddleware;
 
@@ -46,10 +51,20 @@ pub fn routes() -> Vec<Route<AuthMiddleware>> {
         Route {
             auth: None,
             prefix: String::from("/api/v1"),
-            controllers: vec![Controller {
-                path: String::from("/auth"),
-                handler: auth_controller,
-            }],
+            controllers: vec![
+                Controller {
+                    path: String::from("/auth"),
+                    handler: auth_controller,
+                },
+                Controller {
+                    path: String::from("/send"),
+                    handler: mail_controller,
+                },
+                Controller {
+                    path: String::from("/bounces"),
+                    handler: bounce_controller,
+                },
+            ],
         },
         Route {
             auth: Some(AuthMiddleware::new(vec![])),
@@ -99,6 +114,18 @@ pub fn routes() -> Vec<Route<AuthMiddleware>> {
                     path: String::from("/notifications"),
                     handler: notification_controller,
                 },
+                Controller {
+                    path: String::from("/templates"),
+                    handler: template_controller,
+                },
+                Controller {
+                    path: String::from("/smtp-relays"),
+                    handler: smtp_relay_controller,
+                },
+                Controller {
+                    path: String::from("/suppressions"),
+                    handler: suppression_controller,
+                },
             ],
         },
     ];
@@ -1,7 +1,7 @@
 use std::env;
 
 use actix_files::Files;
-use actix_web::web::{Data, JsonConfig};
+use actix_web::web::{Data, JsonConfig, PayloadConfig};
 use actix_web::App;
 use actix_web::HttpServer;
 use env_logger::Env;
@@ -30,11 +30,13 @@ async fn main() -> std::io::Result<()> {
         port, workers
     );
 
-    let app_state = make_app_state().await;
+    let app_state = make_app_state().await?;
 
+    let body_limit = app_state.max_request_body_size();
     HttpServer::new(move || {
         App::new()
-            .app_data(JsonConfig::default().limit(5 * 1024 * 1024))
+            .app_data(JsonConfig::default().limit(body_limit))
+            .app_data(PayloadConfig::default().limit(body_limit))
             .app_data(Data::new(app_state.clone()))
             .app_data(Data::new(app_state.database().clone()))
             .service(Files::new("/resources/static", "./resources/static"))
@@ -1,18 +1,21 @@
+use std::collections::HashMap;
 use std::env;
+use std::io;
 use std::path::Path;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use diesel::r2d2::ConnectionManager;
 use diesel::PgConnection;
-use lettre::transport::smtp::authentication::{Credentials, Mechanism};
-use lettre::SmtpTransport;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, Tokio1Executor};
 use log::info;
 use mobc::Pool;
 use redis::Client;
 use tera::Tera;
 
-use crate::app_state::{AppRedisQueues, AppServices, AppState};
+use crate::app_state::{AppRedisQueues, AppServices, AppState, DomainLimit, SmtpPoolConfig};
 use crate::helpers::fs::get_cwd;
 use crate::models::mail::MailBox;
 use crate::models::DBPool;
@@ -20,20 +23,23 @@ use crate::redis::{RedisConnectionManager, RedisPool};
 use crate::services::cache_service::CacheService;
 use crate::services::redis_next_service::RedisNextService;
 use crate::services::redis_service::RedisService;
+use crate::services::smtp_relay_service::SmtpRelayService;
 use crate::MAILER;
 
+/// used when `MAILER_REDIS_POOL_MAX_OPEN` is not set
 const CACHE_POOL_MAX_OPEN: u64 = 16;
 const CACHE_POOL_MAX_IDLE: u64 = 8;
 const CACHE_POOL_TIMEOUT_SECONDS: u64 = 1;
 const CACHE_POOL_EXPIRE_SECONDS: u64 = 60;
 
-pub async fn make_app_state() -> AppState {
-    let app = create_app_state().await;
+/// Fails with an `InvalidInput` error when the configuration cannot be made sense of
+pub async fn make_app_state() -> io::Result<AppState> {
+    let app = create_app_state().await?;
     let _ = MAILER.set(app.clone());
-    app
+    Ok(app)
 }
 
-async fn create_app_state() -> AppState {
+async fn create_app_state() -> io::Result<AppState> {
     let database_pool = establish_database_connection();
 
     // templating
@@ -43,9 +49,13 @@ async fn create_app_state() -> AppState {
     let redis = establish_redis_connection();
     let redis_service = RedisService::new(redis.clone());
 
-    let redis_pool = establish_redis_connection_pool();
+    let redis_pool = establish_redis_connection_pool()?;
+    let smtp_pool = get_smtp_pool_config()?;
 
-    AppState {
+    let domain_limits =
+        get_domain_limits().map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    Ok(AppState {
         app_name: env::var("MAILER_APP_NAME").unwrap(),
         app_desc: env::var("MAILER_APP_DESC").unwrap(),
         app_key: env::var("MAILER_APP_KEY").unwrap(),
@@ -65,18 +75,40 @@ async fn create_app_state() -> AppState {
         tera: tera_templating,
         database: database_pool.clone(),
         redis: redis.clone(),
-        smtp: create_smtp_client(),
+        smtp: create_smtp_client(&smtp_pool),
+        smtp_pool,
+        smtp_relays: Arc::new(Mutex::new(HashMap::new())),
         pulse_count: Arc::new(Mutex::new(0)),
         allowed_origins: get_allowed_origins(),
         mail_from: MailBox {
             email: env::var("MAILER_MAIL_FROM_EMAIL").unwrap(),
             name: env::var("MAILER_MAIL_FROM_NAME").unwrap(),
         },
+        bounce_domain: env::var("MAILER_BOUNCE_DOMAIN")
+            .ok()
+            .filter(|domain| !domain.is_empty()),
+        bounce_token: env::var("MAILER_BOUNCE_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty()),
         max_retrials: env::var("MAILER_MAX_RETRIALS").unwrap().parse().unwrap(),
+        retry_backoff: env_or("MAILER_RETRY_BACKOFF_SECONDS", 30)?,
+        retry_backoff_max: env_or("MAILER_RETRY_BACKOFF_MAX_SECONDS", 3600)?,
+        retry_jitter: env_or("MAILER_RETRY_JITTER_PERCENT", 20)?,
+        webhook_max_retrials: env_or("MAILER_WEBHOOK_MAX_RETRIALS", 5)?,
+        webhook_timeout: env_or("MAILER_WEBHOOK_TIMEOUT_SECONDS", 10)?,
+        webhook_backoff: env_or("MAILER_WEBHOOK_BACKOFF_SECONDS", 30)?,
+        signature_tolerance: env_or("MAILER_SIGNATURE_TOLERANCE_SECONDS", 300)?,
+        idempotency_ttl: env_or("MAILER_IDEMPOTENCY_TTL_SECONDS", 86400)?,
+        worker_heartbeat_ttl: env_or("MAILER_WORKER_HEARTBEAT_TTL_SECONDS", 30)?,
+        send_rate_per_second: env_or("MAILER_SEND_RATE_PER_SECOND", 50.0)?,
+        domain_limits,
+        normal_queue_weight: env_or("MAILER_QUEUE_WEIGHT_NORMAL", 4)?,
+        bulk_queue_weight: env_or("MAILER_QUEUE_WEIGHT_BULK", 1)?,
         max_image_upload_size: env::var("MAILER_MAX_IMAGE_UPLOAD_SIZE")
             .unwrap()
             .parse()
             .unwrap(),
+        max_attachment_size: env_or("MAILER_MAX_ATTACHMENT_SIZE", 10_485_760)?,
 
         // redis
         redis_queues: get_redis_queues(),
@@ -86,7 +118,7 @@ async fn create_app_state() -> AppState {
             cache: CacheService::new(redis_service),
             redis_next: RedisNextService::new(redis_pool),
         },
-    }
+    })
 }
 
 pub fn get_server_host_config() -> (String, u16) {
@@ -100,16 +132,17 @@ pub fn establish_redis_connection() -> Client {
     Client::open(redis_url).unwrap()
 }
 
-pub fn establish_redis_connection_pool() -> RedisPool {
+pub fn establish_redis_connection_pool() -> io::Result<RedisPool> {
     let redis_url: String = env::var("MAILER_REDIS_DSN").unwrap();
     let client = Client::open(redis_url).unwrap();
     let manager = RedisConnectionManager::new(client);
-    Pool::builder()
+    let max_open = env_or("MAILER_REDIS_POOL_MAX_OPEN", CACHE_POOL_MAX_OPEN)?;
+    Ok(Pool::builder()
         .get_timeout(Some(Duration::from_secs(CACHE_POOL_TIMEOUT_SECONDS)))
-        .max_open(CACHE_POOL_MAX_OPEN)
+        .max_open(max_open)
         .max_idle(CACHE_POOL_MAX_IDLE)
         .max_lifetime(Some(Duration::from_secs(CACHE_POOL_EXPIRE_SECONDS)))
-        .build(manager)
+        .build(manager))
 }
 
 pub fn establish_database_connection() -> DBPool {
@@ -131,7 +164,7 @@ pub fn get_redis_queues() -> AppRedisQueues {
     }
 }
 
-pub(crate) fn create_smtp_client() -> SmtpTransport {
+pub(crate) fn create_smtp_client(pool: &SmtpPoolConfig) -> AsyncSmtpTransport<Tokio1Executor> {
     let host = env::var("MAILER_MAIL_HOST").unwrap();
     let port: u16 = env::var("MAILER_MAIL_PORT").unwrap().parse().unwrap();
     let username = env::var("MAILER_MAIL_USERNAME").unwrap();
@@ -140,35 +173,80 @@ pub(crate) fn create_smtp_client() -> SmtpTransport {
     let credentials = Credentials::new(username.clone(), password);
 
     info!(
-        "creating smtp client: smtp://{}:[password]@{}:{}",
+        "creating smtp client: smtp://{}:[password]@{}:{} (pool size: {})",
         username,
         host.clone(),
-        port.clone()
+        port.clone(),
+        pool.size
     );
 
-    SmtpTransport::builder_dangerous(host.as_str())
-        .port(port)
-        .build();
-
-    match encryption.as_str() {
-        "local" => SmtpTransport::builder_dangerous(host.as_str())
-            .port(port)
-            .build(),
-        "basic" => SmtpTransport::builder_dangerous(host.as_str())
-            .port(port)
-            .credentials(credentials)
-            .build(),
-        "startls" | "tls" => SmtpTransport::starttls_relay(host.as_str())
-            .unwrap()
-            .credentials(credentials)
-            .authentication(vec![Mechanism::Login])
-            .build(),
-        _ => {
-            panic!("Encryption must be one of (local, basic, startls, tls)")
-        }
+    match SmtpRelayService::build_transport(&host, port, &encryption, Some(credentials), pool) {
+        Ok(transport) => transport,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+pub fn get_smtp_pool_config() -> io::Result<SmtpPoolConfig> {
+    Ok(SmtpPoolConfig {
+        size: env_or("MAILER_MAIL_POOL_SIZE", 10)?,
+        idle_timeout: env_or("MAILER_MAIL_POOL_IDLE_TIMEOUT_SECONDS", 60)?,
+        timeout: env_or("MAILER_MAIL_TIMEOUT_SECONDS", 30)?,
+    })
+}
+
+/// Reads a setting that older deployments may not have, `default` is used when it is unset or empty
+fn env_or<T: FromStr>(name: &str, default: T) -> io::Result<T> {
+    match env::var(name) {
+        Ok(value) if !value.trim().is_empty() => value.trim().parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid {}: '{}'", name, value),
+            )
+        }),
+        _ => Ok(default),
     }
 }
 
+/// Parses `domain:concurrency:rate_per_second` entries, e.g. `gmail.com:10:50,*:20:100`
+pub fn get_domain_limits() -> Result<HashMap<String, DomainLimit>, String> {
+    parse_domain_limits(&env::var("MAILER_DOMAIN_LIMITS").unwrap_or_default())
+}
+
+/// parses comma separated `<domain>:<concurrency>:<rate_per_second>` entries
+pub fn parse_domain_limits(limits_str: &str) -> Result<HashMap<String, DomainLimit>, String> {
+    limits_str
+        .split(',')
+        .map(|entry| entry.trim())
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let invalid = || {
+                format!(
+                    "invalid MAILER_DOMAIN_LIMITS entry '{}': expected <domain>:<concurrency>:<rate_per_second>",
+                    entry
+                )
+            };
+
+            let parts: Vec<&str> = entry.split(':').map(|part| part.trim()).collect();
+            if parts.len() != 3 || parts[0].is_empty() {
+                return Err(invalid());
+            }
+
+            let concurrency: i64 = parts[1].parse().map_err(|_| invalid())?;
+            let rate_per_second: f64 = parts[2].parse().map_err(|_| invalid())?;
+            if concurrency < 1 || !rate_per_second.is_finite() || rate_per_second <= 0.0 {
+                return Err(invalid());
+            }
+
+            let limit = DomainLimit {
+                concurrency,
+                rate_per_second,
+            };
+
+            Ok((parts[0].to_lowercase(), limit))
+        })
+        .collect()
+}
+
 pub fn get_allowed_origins() -> Vec<String> {
     let url_str = env::var("MAILER_ALLOWED_ORIGINS").unwrap();
     let origins: Vec<&str> = url_str.split(',').collect();
@@ -223,3 +301,80 @@ pub fn get_worker_configs() -> (i8, Vec<String>) {
 
     (tasks_per_worker, workers)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_the_default_when_unset() {
+        assert_eq!(env_or("MAILER_TEST_ENV_OR_UNSET", 30).unwrap(), 30);
+
+        unsafe { env::set_var("MAILER_TEST_ENV_OR_EMPTY", " ") };
+        assert_eq!(env_or("MAILER_TEST_ENV_OR_EMPTY", 30).unwrap(), 30);
+    }
+
+    #[test]
+    fn reads_a_set_value() {
+        unsafe { env::set_var("MAILER_TEST_ENV_OR_SET", " 12 ") };
+        assert_eq!(env_or("MAILER_TEST_ENV_OR_SET", 30).unwrap(), 12);
+    }
+
+    #[test]
+    fn rejects_a_malformed_value() {
+        unsafe { env::set_var("MAILER_TEST_ENV_OR_MALFORMED", "thirty") };
+        let error = env_or("MAILER_TEST_ENV_OR_MALFORMED", 30).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error.to_string().contains("MAILER_TEST_ENV_OR_MALFORMED"));
+    }
+
+    #[test]
+    fn parses_domain_limits() {
+        let limits = parse_domain_limits(" Gmail.com:10:20 , *:50:0.5,").unwrap();
+        assert_eq!(limits.len(), 2);
+
+        let gmail = limits.get("gmail.com").unwrap();
+        assert_eq!(gmail.concurrency, 10);
+        assert_eq!(gmail.rate_per_second, 20.0);
+
+        let fallback = limits.get("*").unwrap();
+        assert_eq!(fallback.concurrency, 50);
+        assert_eq!(fallback.rate_per_second, 0.5);
+    }
+
+    #[test]
+    fn allows_no_domain_limits() {
+        assert!(parse_domain_limits("").unwrap().is_empty());
+        assert!(parse_domain_limits(" , ,").unwrap().is_empty());
+    }
+
+    #[test]
+    fn keeps_the_last_limit_of_a_repeated_domain() {
+        let limits = parse_domain_limits("gmail.com:10:20,GMAIL.com:5:1").unwrap();
+        assert_eq!(limits.len(), 1);
+        assert_eq!(limits.get("gmail.com").unwrap().concurrency, 5);
+    }
+
+    #[test]
+    fn rejects_malformed_domain_limits() {
+        for entry in [
+            "gmail.com",
+            "gmail.com:10",
+            "gmail.com:10:20:30",
+            ":10:20",
+            "gmail.com:ten:20",
+            "gmail.com:10:fast",
+            "gmail.com:0:20",
+            "gmail.com:-1:20",
+            "gmail.com:10:0",
+            "gmail.com:10:-5",
+            "gmail.com:10:NaN",
+            "gmail.com:10:inf",
+        ] {
+            let error = parse_domain_limits(&format!("outlook.com:10:20,{}", entry))
+                .err()
+                .unwrap_or_else(|| panic!("accepted {}", entry));
+            assert!(error.contains(&format!("'{}'", entry)), "{}", error);
+        }
+    }
+}
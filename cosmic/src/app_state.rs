@@ -1,15 +1,22 @@
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
-use lettre::SmtpTransport;
+use chrono::NaiveDateTime;
+use lettre::{AsyncSmtpTransport, Tokio1Executor};
 use redis::Client;
 use tera::{Context, Tera};
+use uuid::Uuid;
 
+use crate::helpers::template::render_untrusted;
 use crate::helpers::DBPool;
-use crate::models::mail::MailBox;
+use crate::models::mail::{MailBox, MailPriority};
 use crate::services::cache_service::CacheService;
 use crate::services::redis_next_service::RedisNextService;
 use crate::services::redis_service::RedisService;
 
+/// room left in request bodies for everything but the attachments
+const REQUEST_BODY_OVERHEAD: usize = 1024 * 1024;
+
 #[derive(Clone)]
 pub struct AppState {
     pub app_name: String,
@@ -24,15 +31,40 @@ pub struct AppState {
     pub auth_pat_prefix: String,
 
     pub mail_from: MailBox,
+    /// domain of the per-mail (VERP) return path, bounces go to the mail's own address when absent
+    pub bounce_domain: Option<String>,
+    /// shared secret delivery status notifications are posted with, bounce processing is off when unset
+    pub bounce_token: Option<String>,
     pub mailer_application_id: String,
     pub mailer_system_user_id: String,
 
     pub max_image_upload_size: u64,
+    /// largest total attachment size an application may be allowed
+    pub max_attachment_size: u64,
     pub tera: Tera,
-    pub smtp: SmtpTransport,
+    pub smtp: AsyncSmtpTransport<Tokio1Executor>,
+    pub smtp_pool: SmtpPoolConfig,
+    /// transports of the configured relays, rebuilt once the relay is updated
+    pub smtp_relays: Arc<Mutex<HashMap<Uuid, SmtpRelayTransport>>>,
     pub database: DBPool,
     pub redis: Client,
     pub max_retrials: i16,
+    pub retry_backoff: i64,
+    pub retry_backoff_max: i64,
+    pub retry_jitter: i64,
+    pub webhook_max_retrials: i16,
+    pub webhook_timeout: u64,
+    pub webhook_backoff: i64,
+    pub signature_tolerance: i64,
+    pub idempotency_ttl: u64,
+    pub worker_heartbeat_ttl: u64,
+    /// mails an application may send per second unless it has its own limit
+    pub send_rate_per_second: f64,
+    /// caps per recipient domain, `*` applies to domains not listed
+    pub domain_limits: HashMap<String, DomainLimit>,
+    /// how often normal & bulk mails are taken first, high priority mails always are
+    pub normal_queue_weight: u32,
+    pub bulk_queue_weight: u32,
     pub pulse_count: Arc<Mutex<i32>>,
     pub allowed_origins: Vec<String>,
     pub redis_queues: AppRedisQueues,
@@ -40,6 +72,26 @@ pub struct AppState {
     pub services: AppServices,
 }
 
+#[derive(Clone)]
+pub struct SmtpPoolConfig {
+    pub size: u32,
+    pub idle_timeout: u64,
+    pub timeout: u64,
+}
+
+#[derive(Clone)]
+pub struct DomainLimit {
+    /// mails being sent to the domain at once
+    pub concurrency: i64,
+    pub rate_per_second: f64,
+}
+
+#[derive(Clone)]
+pub struct SmtpRelayTransport {
+    pub updated_at: NaiveDateTime,
+    pub transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
 #[derive(Clone)]
 pub struct AppServices {
     pub redis: RedisService,
@@ -57,24 +109,48 @@ pub struct AppRedisQueues {
     pub callback: String,
 }
 
+impl AppRedisQueues {
+    /// Processing list of the priority, normal mails go through the base list
+    pub fn processing_of(&self, priority: MailPriority) -> String {
+        match priority {
+            MailPriority::Normal => self.processing.clone(),
+            _ => format!("{}:{}", self.processing, priority),
+        }
+    }
+
+    /// List idle processing workers wait on, pushed to whenever a processing list receives a mail
+    pub fn processing_signal(&self) -> String {
+        format!("{}:signal", self.processing)
+    }
+}
+
 impl AppState {
     pub fn database(&self) -> &DBPool {
         &self.database
     }
 
+    /// Largest request body accepted, attachments are submitted base64 encoded (4/3 of their size)
+    /// next to the rest of the payload
+    pub fn max_request_body_size(&self) -> usize {
+        (self.max_attachment_size * 4 / 3) as usize + REQUEST_BODY_OVERHEAD
+    }
+
     pub fn title(&self, text: &str) -> String {
         format!("{} - {}", text, self.app_name)
     }
 
-    pub fn render(&self, file: String, context: Context) -> String {
+    pub fn render(&self, file: String, context: &Context) -> tera::Result<String> {
         let mut filename = file;
         if !filename.ends_with(".tera.html") {
             filename.push_str(".tera.html");
         }
 
-        match self.tera.render(&filename, &context) {
-            Ok(string) => string,
-            Err(error) => panic!("{}", error),
-        }
+        self.tera.render(&filename, context)
+    }
+
+    /// Renders a template that is not part of `resources/templates` on a standalone instance,
+    /// so that the bundled templates are not copied for each render, `.html` names are auto-escaped
+    pub fn render_raw(&self, name: &str, source: &str, context: &Context) -> tera::Result<String> {
+        render_untrusted(name, source, context)
     }
 }
@@ -35,6 +35,8 @@ pub enum AppMessage {
     SuccessMessageStr(&'static str),
     ErrorMessage(String, StatusCode),
     UnAuthorizedMessage(&'static str),
+    /// message & seconds after which the request may be retried
+    TooManyRequests(String, u64),
     FormValidationError(ValidationErrors),
     BlockingError(actix_web::error::BlockingError),
     JoinError(tokio::task::JoinError),
@@ -202,6 +204,7 @@ impl ResponseError for AppMessage {
             AppMessage::ErrorMessage(_, status) => *status,
             AppMessage::UnAuthorized => StatusCode::UNAUTHORIZED,
             AppMessage::UnAuthorizedMessage(_) => StatusCode::UNAUTHORIZED,
+            AppMessage::TooManyRequests(_, _) => StatusCode::TOO_MANY_REQUESTS,
             AppMessage::FormValidationError(_) => StatusCode::BAD_REQUEST,
             AppMessage::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
             _ => StatusCode::INTERNAL_SERVER_ERROR, // all database-related errors are 500
@@ -241,6 +244,7 @@ fn get_message(status: &AppMessage) -> String {
         AppMessage::SuccessMessageStr(message) => message.to_string(),
         AppMessage::ErrorMessage(message, _) => message.clone(),
         AppMessage::UnAuthorizedMessage(message) => message.to_string(),
+        AppMessage::TooManyRequests(message, _) => message.clone(),
         AppMessage::FormValidationError(e) => String::from(e.to_string().as_str()),
         _ => String::from("Internal Server Error"),
     }
@@ -283,6 +287,14 @@ pub fn send_response(status: &AppMessage) -> HttpResponse {
         AppMessage::UnAuthorizedMessage(message) => {
             json_error_message_status(message, StatusCode::UNAUTHORIZED)
         }
+        AppMessage::TooManyRequests(message, retry_after) => {
+            let mut resp = json_error_message_status(message, StatusCode::TOO_MANY_REQUESTS);
+            resp.headers_mut().insert(
+                "Retry-After".parse().unwrap(),
+                retry_after.to_string().parse().unwrap(),
+            );
+            resp
+        }
         AppMessage::FormValidationError(e) => {
             json_error(e, StatusCode::BAD_REQUEST, Some(string("Validation Error")))
         }
@@ -175,4 +175,31 @@ pub enum AuthPermission {
     UserJobTitleAssign,
     UserJobTitleDelete,
     MailSend,
+    MailList,
+    MailRead,
+    WebhookDeliveryList,
+    MailTemplateList,
+    MailTemplateCreate,
+    MailTemplateRead,
+    MailTemplateUpdate,
+    MailTemplateDelete,
+    MailTemplatePreview,
+    MailTemplateTestSend,
+    MailSchedule,
+    MailCancel,
+    SmtpRelayList,
+    SmtpRelayCreate,
+    SmtpRelayRead,
+    SmtpRelayUpdate,
+    SmtpRelayDelete,
+    SmtpRelayManageAll,
+    ApplicationSmtpRelayList,
+    ApplicationSmtpRelayAssign,
+    ApplicationLimitUpdate,
+    SuppressionList,
+    SuppressionCreate,
+    SuppressionRead,
+    SuppressionUpdate,
+    SuppressionDelete,
+    SuppressionManageGlobal,
 }
@@ -0,0 +1,96 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::helpers::hmac::hmac_hash;
+
+/// Encrypts with AES-256-GCM under a key derived from `secret` for the given purpose,
+/// the random nonce is prepended to the ciphertext and the whole is base64 encoded
+pub fn encrypt(plaintext: &str, secret: &str, purpose: &str) -> String {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .expect("system random generator is available");
+
+    let mut sealed = plaintext.as_bytes().to_vec();
+    make_key(secret, purpose)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::empty(),
+            &mut sealed,
+        )
+        .expect("plaintext fits in a single AES-GCM message");
+
+    let mut encrypted = nonce.to_vec();
+    encrypted.extend(sealed);
+    STANDARD.encode(encrypted)
+}
+
+/// Reverses `encrypt`, None when the value was tampered with or encrypted under another secret
+pub fn decrypt(encrypted: &str, secret: &str, purpose: &str) -> Option<String> {
+    let decoded = STANDARD.decode(encrypted).ok()?;
+    if decoded.len() < NONCE_LEN {
+        return None;
+    }
+
+    let (nonce, sealed) = decoded.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+    let mut sealed = sealed.to_vec();
+    let plaintext = make_key(secret, purpose)
+        .open_in_place(nonce, Aad::empty(), &mut sealed)
+        .ok()?;
+
+    String::from_utf8(plaintext.to_vec()).ok()
+}
+
+fn make_key(secret: &str, purpose: &str) -> LessSafeKey {
+    let key = hex::decode(hmac_hash(purpose.to_string(), secret.to_string())).unwrap();
+    LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &key).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decrypts_what_was_encrypted() {
+        let encrypted = encrypt("Pass.1234", "secret", "purpose");
+        assert_ne!(encrypted, "Pass.1234");
+        assert_eq!(
+            decrypt(&encrypted, "secret", "purpose").as_deref(),
+            Some("Pass.1234")
+        );
+    }
+
+    #[test]
+    fn uses_a_fresh_nonce_per_value() {
+        assert_ne!(
+            encrypt("Pass.1234", "secret", "purpose"),
+            encrypt("Pass.1234", "secret", "purpose")
+        );
+    }
+
+    #[test]
+    fn rejects_another_secret_or_purpose() {
+        let encrypted = encrypt("Pass.1234", "secret", "purpose");
+        assert_eq!(decrypt(&encrypted, "other", "purpose"), None);
+        assert_eq!(decrypt(&encrypted, "secret", "other"), None);
+    }
+
+    #[test]
+    fn rejects_tampered_or_malformed_values() {
+        let mut encrypted = STANDARD
+            .decode(encrypt("Pass.1234", "secret", "purpose"))
+            .unwrap();
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 1;
+
+        assert_eq!(
+            decrypt(&STANDARD.encode(encrypted), "secret", "purpose"),
+            None
+        );
+        assert_eq!(decrypt("not base64!", "secret", "purpose"), None);
+        assert_eq!(decrypt("", "secret", "purpose"), None);
+    }
+}
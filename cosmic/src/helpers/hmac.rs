@@ -15,6 +15,51 @@ pub fn hmac_hash(value: String, secret: String) -> String {
     hex::encode(code_bytes.as_slice())
 }
 
+/// Signs the payload together with the given unix timestamp,
+/// the result is meant to be sent as the `X-Mailer-Signature` header: `t=<timestamp>,v1=<hex>`
+pub fn hmac_sign_timestamped(payload: &str, secret: &str, timestamp: i64) -> String {
+    let signature = hmac_hash(format!("{}.{}", timestamp, payload), secret.to_string());
+    format!("t={},v1={}", timestamp, signature)
+}
+
+/// Verifies an `X-Mailer-Signature` header against the raw payload,
+/// signatures older (or newer) than `tolerance` seconds are rejected to prevent replays
+pub fn hmac_verify_signature(header: &str, payload: &str, secret: &str, tolerance: i64) -> bool {
+    type HmacSha256 = Hmac<Sha256>;
+
+    let mut timestamp = None;
+    let mut signatures = vec![];
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.push(value),
+            _ => {}
+        }
+    }
+
+    let timestamp = match timestamp {
+        Some(timestamp) => timestamp,
+        None => return false,
+    };
+
+    let age = Utc::now().timestamp().abs_diff(timestamp);
+    if i64::try_from(age).map_or(true, |age| age > tolerance) {
+        return false;
+    }
+
+    let signed = format!("{}.{}", timestamp, payload);
+    signatures.into_iter().any(|signature| {
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+
+        let mut mac =
+            HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+        mac.update(signed.as_bytes());
+        mac.verify_slice(&signature).is_ok()
+    })
+}
+
 pub fn hmac_generate_random() -> String {
     type HmacSha256 = Hmac<Sha256>;
 
@@ -29,3 +74,101 @@ pub fn hmac_generate_random() -> String {
     let code_bytes = result.into_bytes();
     hex::encode(code_bytes.as_slice())
 }
+
+/// Compares secrets in constant time
+pub fn secure_compare(left: &str, right: &str) -> bool {
+    left.len() == right.len()
+        && left
+            .bytes()
+            .zip(right.bytes())
+            .fold(0, |diff, (l, r)| diff | (l ^ r))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYLOAD: &str = r#"{"event":"mail.sent"}"#;
+
+    #[test]
+    fn verifies_what_was_signed() {
+        let header = hmac_sign_timestamped(PAYLOAD, "secret", Utc::now().timestamp());
+        assert!(hmac_verify_signature(&header, PAYLOAD, "secret", 300));
+    }
+
+    #[test]
+    fn rejects_another_payload_or_secret() {
+        let header = hmac_sign_timestamped(PAYLOAD, "secret", Utc::now().timestamp());
+        assert!(!hmac_verify_signature(
+            &header,
+            r#"{"event":"mail.failed"}"#,
+            "secret",
+            300
+        ));
+        assert!(!hmac_verify_signature(&header, PAYLOAD, "other", 300));
+    }
+
+    #[test]
+    fn rejects_signatures_outside_the_tolerance() {
+        let now = Utc::now().timestamp();
+        for timestamp in [now - 301, now + 301, 0, i64::MIN, i64::MAX] {
+            let header = hmac_sign_timestamped(PAYLOAD, "secret", timestamp);
+            assert!(
+                !hmac_verify_signature(&header, PAYLOAD, "secret", 300),
+                "{}",
+                header
+            );
+        }
+
+        let header = hmac_sign_timestamped(PAYLOAD, "secret", now - 200);
+        assert!(hmac_verify_signature(&header, PAYLOAD, "secret", 300));
+    }
+
+    #[test]
+    fn rejects_a_timestamp_swapped_after_signing() {
+        let now = Utc::now().timestamp();
+        let header = hmac_sign_timestamped(PAYLOAD, "secret", now - 1000);
+        let forged = header.replace(&format!("t={}", now - 1000), &format!("t={}", now));
+        assert!(!hmac_verify_signature(&forged, PAYLOAD, "secret", 300));
+    }
+
+    #[test]
+    fn accepts_any_of_several_signatures() {
+        let now = Utc::now().timestamp();
+        let valid = hmac_sign_timestamped(PAYLOAD, "secret", now);
+        let rotated = hmac_sign_timestamped(PAYLOAD, "old", now);
+        let header = format!("{}, {}", rotated, valid.split(',').nth(1).unwrap());
+        assert!(hmac_verify_signature(&header, PAYLOAD, "secret", 300));
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        let now = Utc::now().timestamp();
+        let signature = hmac_hash(format!("{}.{}", now, PAYLOAD), "secret".to_string());
+        for header in [
+            String::new(),
+            format!("v1={}", signature),
+            format!("t=abc,v1={}", signature),
+            format!("t={}", now),
+            format!("t={},v1=not-hex", now),
+            format!("t={},v2={}", now, signature),
+            format!("t={},v1={}", now, &signature[..32]),
+        ] {
+            assert!(
+                !hmac_verify_signature(&header, PAYLOAD, "secret", 300),
+                "{}",
+                header
+            );
+        }
+    }
+
+    #[test]
+    fn compares_secrets() {
+        assert!(secure_compare("token", "token"));
+        assert!(secure_compare("", ""));
+        assert!(!secure_compare("token", "tokem"));
+        assert!(!secure_compare("token", "token1"));
+        assert!(!secure_compare("", "token"));
+    }
+}
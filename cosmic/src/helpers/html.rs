@@ -0,0 +1,176 @@
+/// Derives a readable plain-text version of an html document,
+/// block elements become line breaks and links keep their target next to the label
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut skipping: Option<String> = None;
+    let mut links: Vec<Option<String>> = vec![];
+    let mut chars = html.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '<' {
+            if skipping.is_none() {
+                text.push(if c.is_whitespace() { ' ' } else { c });
+            }
+            continue;
+        }
+
+        let mut tag = String::new();
+        for c in chars.by_ref() {
+            if c == '>' {
+                break;
+            }
+            tag.push(c);
+        }
+
+        let tag = tag.trim().to_string();
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+
+        if let Some(skipped) = &skipping {
+            if closing && name == *skipped {
+                skipping = None;
+            }
+            continue;
+        }
+
+        match name.as_str() {
+            "script" | "style" | "head" | "title" if !closing => skipping = Some(name),
+            "br" | "hr" => text.push('\n'),
+            "li" if !closing => text.push_str("\n- "),
+            "p" | "div" | "tr" | "table" | "ul" | "ol" | "blockquote" | "section" | "h1" | "h2"
+            | "h3" | "h4" | "h5" | "h6" => text.push('\n'),
+            "td" | "th" if closing => text.push(' '),
+            "a" if !closing => links.push(attribute(&tag, "href")),
+            "a" => {
+                if let Some(Some(href)) = links.pop()
+                    && !href.starts_with('#')
+                    && !href.starts_with("mailto:")
+                {
+                    text.push_str(&format!(" ({})", href));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let text = decode_entities(&text);
+
+    let mut lines: Vec<String> = vec![];
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+
+    lines.join("\n").trim().to_string()
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_lowercase();
+    let needle = format!("{}=", name);
+    // skip matches inside longer attribute names, e.g. `data-href=`
+    let start = lower
+        .match_indices(&needle)
+        .map(|(index, _)| index)
+        .find(|index| lower[..*index].ends_with(char::is_whitespace))?
+        + needle.len();
+    let value = &tag[start..];
+    let value = match value.chars().next()? {
+        quote @ ('"' | '\'') => value[1..].split(quote).next()?,
+        _ => value.split_whitespace().next()?,
+    };
+
+    Some(decode_entities(value))
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&copy;", "©")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separates_blocks_with_a_single_blank_line() {
+        let html = "<h1>Welcome</h1><p>Hello&nbsp;John,</p><div>Thanks<br/>The team</div>";
+        assert_eq!(
+            html_to_text(html),
+            "Welcome\n\nHello John,\n\nThanks\nThe team"
+        );
+    }
+
+    #[test]
+    fn collapses_whitespace_and_blank_lines() {
+        let html = "<p>  Hello \n\t  world  </p>\n\n<p></p><p></p><p>Bye</p>";
+        assert_eq!(html_to_text(html), "Hello world\n\nBye");
+    }
+
+    #[test]
+    fn skips_head_scripts_and_styles() {
+        let html = "<html><head><title>Subject</title><style>p { color: red; }</style></head>\
+            <body><script type=\"text/javascript\">alert('<p>');</script><p>Body</p></body></html>";
+        assert_eq!(html_to_text(html), "Body");
+    }
+
+    #[test]
+    fn keeps_link_targets_next_to_labels() {
+        let html = "<a href=\"https://example.com/verify?a=1&amp;b=2\">Verify</a> \
+            <a href='#top'>Top</a> <a href=mailto:me@example.com>Mail</a> <a>None</a>";
+        assert_eq!(
+            html_to_text(html),
+            "Verify (https://example.com/verify?a=1&b=2) Top Mail None"
+        );
+    }
+
+    #[test]
+    fn reads_unquoted_and_uppercase_attributes() {
+        assert_eq!(
+            html_to_text("<A HREF=https://example.com class=link>Go</A>"),
+            "Go (https://example.com)"
+        );
+    }
+
+    #[test]
+    fn ignores_attributes_ending_with_the_name() {
+        assert_eq!(
+            html_to_text("<a data-href=\"#ignored\" href=\"https://example.com\">Go</a>"),
+            "Go (https://example.com)"
+        );
+    }
+
+    #[test]
+    fn lists_and_table_cells() {
+        let html = "<ul><li>One</li><li>Two</li></ul><table><tr><td>A</td><td>B</td></tr></table>";
+        assert_eq!(html_to_text(html), "- One\n- Two\n\nA B");
+    }
+
+    #[test]
+    fn decodes_entities_once() {
+        assert_eq!(
+            html_to_text("<p>&lt;b&gt; &quot;x&quot; &#39;y&apos; &amp;lt; &copy;</p>"),
+            "<b> \"x\" 'y' &lt; ©"
+        );
+    }
+
+    #[test]
+    fn handles_empty_and_plain_input() {
+        assert_eq!(html_to_text(""), "");
+        assert_eq!(html_to_text("just text"), "just text");
+        assert_eq!(html_to_text("<p>unterminated"), "unterminated");
+    }
+}
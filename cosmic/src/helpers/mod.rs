@@ -3,11 +3,13 @@ use diesel::PgConnection;
 use r2d2::PooledConnection;
 
 pub mod auth;
+pub mod crypto;
 pub mod db;
 pub mod db_pagination;
 pub mod form;
 pub mod fs;
 pub mod hmac;
+pub mod html;
 pub mod http;
 pub mod id_generator;
 pub mod misc;
@@ -17,6 +19,7 @@ pub mod request;
 pub mod responder;
 pub mod security;
 pub mod string;
+pub mod template;
 pub mod time;
 pub mod uuid;
 pub mod validator;
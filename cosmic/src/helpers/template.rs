@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+use tera::{Context, Tera, Value};
+
+/// Renders a template stored by an application owner on a standalone instance,
+/// tera's `get_env` is disabled so that stored templates cannot read the server's secrets
+pub fn render_untrusted(name: &str, source: &str, context: &Context) -> tera::Result<String> {
+    let mut tera = Tera::default();
+    tera.register_function("get_env", disabled_get_env);
+    tera.add_raw_template(name, source)?;
+    tera.render(name, context)
+}
+
+fn disabled_get_env(_: &HashMap<String, Value>) -> tera::Result<Value> {
+    Err(tera::Error::msg(
+        "get_env is not available in mail templates",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_the_given_source() {
+        let mut context = Context::new();
+        context.insert("name", "<John>");
+
+        assert_eq!(
+            render_untrusted("greeting.txt", "Hello {{ name }}", &context).unwrap(),
+            "Hello <John>"
+        );
+        assert_eq!(
+            render_untrusted("greeting.html", "Hello {{ name }}", &context).unwrap(),
+            "Hello &lt;John&gt;"
+        );
+    }
+
+    #[test]
+    fn cannot_read_the_environment() {
+        for source in [
+            r#"{{ get_env(name="PATH") }}"#,
+            r#"{{ get_env(name="MAILER_MISSING", default="fallback") }}"#,
+            r#"{% set secret = get_env(name="PATH") %}{{ secret }}"#,
+        ] {
+            assert!(
+                render_untrusted("leak.html", source, &Context::new()).is_err(),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn cannot_include_other_templates() {
+        let source = r#"{% include "emails/otp.tera.html" %}"#;
+        assert!(render_untrusted("include.html", source, &Context::new()).is_err());
+    }
+}
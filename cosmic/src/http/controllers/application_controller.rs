@@ -1,28 +1,55 @@
 use actix_web::web::{block, Data, Json, Path, Query, ServiceConfig};
 use actix_web::{delete, get, patch, post, put, HttpRequest};
 use uuid::Uuid;
+use validator::Validate;
 
 use crate::enums::app_message::AppMessage;
 use crate::enums::auth_permission::AuthPermission;
 use crate::helpers::http::{IdPathParam, QueryParams};
 use crate::helpers::request::RequestHelper;
 use crate::helpers::DBPool;
-use crate::models::application::{ApplicationCreateForm, ApplicationUpdateForm};
-use crate::models::mail::{MailPayload, MailQueueablePayload};
+use crate::models::application::{
+    ApplicationCreateForm, ApplicationLimitsForm, ApplicationUpdateForm,
+};
+use crate::models::mail::{MailFilterParams, MailPayload, MailScheduleForm};
+use crate::models::mail_template::{
+    MailTemplateCreateForm, MailTemplateUpdateForm, MailTemplateVersionParam,
+};
+use crate::models::smtp_relay::ApplicationSmtpRelaysForm;
 use crate::repositories::app_key_repository::AppKeyRepository;
 use crate::repositories::application_repository::ApplicationRepository;
+use crate::repositories::mail_template_repository::MailTemplateRepository;
+use crate::repositories::smtp_relay_repository::SmtpRelayRepository;
+use crate::repositories::webhook_delivery_repository::WebhookDeliveryRepository;
 use crate::results::http_result::ActixBlockingResultResponder;
 use crate::results::HttpResult;
 use crate::services::app_key_service::AppKeyService;
 use crate::services::application_service::ApplicationService;
 use crate::services::mail_service::MailService;
+use crate::services::mail_template_service::MailTemplateService;
+use crate::services::smtp_relay_service::SmtpRelayService;
 
 pub fn application_controller(cfg: &mut ServiceConfig) {
     cfg.service(index);
     cfg.service(show);
     cfg.service(store);
     cfg.service(update);
+    cfg.service(limits);
     cfg.service(mails);
+    cfg.service(mail_index);
+    cfg.service(mail_show);
+    cfg.service(mail_reschedule);
+    cfg.service(mail_unschedule);
+    cfg.service(mail_cancel);
+    cfg.service(webhook_deliveries);
+    cfg.service(smtp_relays);
+    cfg.service(smtp_relays_assign);
+    cfg.service(template_index);
+    cfg.service(template_store);
+    cfg.service(template_show);
+    cfg.service(template_versions);
+    cfg.service(template_update);
+    cfg.service(template_delete);
     cfg.service(delete);
     cfg.service(deactivate);
     cfg.service(activate);
@@ -46,6 +73,7 @@ async fn store(form: Json<ApplicationCreateForm>, req: HttpRequest) -> HttpResul
     let ctx = req.context();
     block(move || {
         ctx.verify_user_permission(AuthPermission::ApplicationKeyGenerate)?;
+        ApplicationService.verify_max_attachment_size(&ctx.app(), form.max_attachment_size)?;
         ApplicationService.create(ctx.database(), ctx.auth_id, form.into_inner())
     })
     .await
@@ -63,39 +91,253 @@ async fn show(id: Path<Uuid>, req: HttpRequest) -> HttpResult {
     .respond()
 }
 
+#[put("{id}/limits")]
+async fn limits(id: Path<Uuid>, form: Json<ApplicationLimitsForm>, req: HttpRequest) -> HttpResult {
+    form.validate()?;
+    let ctx = req.context();
+    block(move || {
+        // limits are set by operators on any application, not by its owner
+        ctx.verify_user_permission(AuthPermission::ApplicationLimitUpdate)?;
+        ApplicationRepository.update_limits(ctx.database(), *id, form.into_inner())
+    })
+    .await
+    .respond()
+}
+
 #[post("{id}/mails")]
 async fn mails(id: Path<Uuid>, req: HttpRequest, form: Json<MailPayload>) -> HttpResult {
     let ctx = req.context();
+    let idempotency_key = req
+        .headers()
+        .get("Idempotency-Key")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+
     block(move || {
         ctx.verify_user_permission(AuthPermission::MailSend)?;
 
         // Verify user has access to this neuron
         let app_id = ApplicationRepository.find_owned_by_id(ctx.database(), *id, ctx.auth_id())?;
 
-        let total_mails = form.mails.len();
-        for mail in form.mails.clone() {
-            let _result = MailService.push_to_awaiting_queue(
-                ctx.app().as_ref(),
-                MailQueueablePayload {
-                    application_id: app_id,
-                    created_by: ctx.auth_id(),
-                    subject: mail.subject,
-                    message: mail.message,
-                    from: mail.from,
-                    cc: mail.cc,
-                    bcc: mail.bcc,
-                    reply_to: mail.reply_to,
-                    receiver: mail.receiver,
-                },
-            );
-        }
+        MailService.enqueue(
+            ctx.app().as_ref(),
+            app_id,
+            ctx.auth_id(),
+            form.into_inner().mails,
+            idempotency_key,
+        )
+    })
+    .await
+    .respond()
+}
+
+#[get("{id}/mails")]
+async fn mail_index(
+    id: Path<Uuid>,
+    q: Query<QueryParams>,
+    filter: Query<MailFilterParams>,
+    req: HttpRequest,
+) -> HttpResult {
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::MailList)?;
+        let app_id = ApplicationRepository.find_owned_by_id(ctx.database(), *id, ctx.auth_id())?;
+        MailService.list(ctx.database(), app_id, q.into_inner(), filter.into_inner())
+    })
+    .await
+    .respond()
+}
+
+#[get("{id}/mails/{mail_id}")]
+async fn mail_show(path: Path<(Uuid, Uuid)>, req: HttpRequest) -> HttpResult {
+    let ctx = req.context();
+    let (id, mail_id) = path.into_inner();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::MailRead)?;
+        let app_id = ApplicationRepository.find_owned_by_id(ctx.database(), id, ctx.auth_id())?;
+        MailService.find_detailed(ctx.database(), app_id, mail_id)
+    })
+    .await
+    .respond()
+}
+
+#[put("{id}/mails/{mail_id}/schedule")]
+async fn mail_reschedule(
+    path: Path<(Uuid, Uuid)>,
+    form: Json<MailScheduleForm>,
+    req: HttpRequest,
+) -> HttpResult {
+    let ctx = req.context();
+    let (id, mail_id) = path.into_inner();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::MailSchedule)?;
+        let app_id = ApplicationRepository.find_owned_by_id(ctx.database(), id, ctx.auth_id())?;
+        MailService.reschedule(ctx.app().as_ref(), app_id, mail_id, form.send_at)
+    })
+    .await
+    .respond()
+}
+
+#[delete("{id}/mails/{mail_id}/schedule")]
+async fn mail_unschedule(path: Path<(Uuid, Uuid)>, req: HttpRequest) -> HttpResult {
+    let ctx = req.context();
+    let (id, mail_id) = path.into_inner();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::MailSchedule)?;
+        let app_id = ApplicationRepository.find_owned_by_id(ctx.database(), id, ctx.auth_id())?;
+        MailService.cancel_scheduled(ctx.app().as_ref(), app_id, mail_id)
+    })
+    .await
+    .respond()
+}
+
+#[post("{id}/mails/{mail_id}/cancel")]
+async fn mail_cancel(path: Path<(Uuid, Uuid)>, req: HttpRequest) -> HttpResult {
+    let ctx = req.context();
+    let (id, mail_id) = path.into_inner();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::MailCancel)?;
+        let app_id = ApplicationRepository.find_owned_by_id(ctx.database(), id, ctx.auth_id())?;
+        MailService.cancel(ctx.app().as_ref(), app_id, mail_id)
+    })
+    .await
+    .respond()
+}
+
+#[get("{id}/webhook-deliveries")]
+async fn webhook_deliveries(id: Path<Uuid>, q: Query<QueryParams>, req: HttpRequest) -> HttpResult {
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::WebhookDeliveryList)?;
+        let app_id = ApplicationRepository.find_owned_by_id(ctx.database(), *id, ctx.auth_id())?;
+        WebhookDeliveryRepository.list_by_application(ctx.database(), app_id, q.into_inner())
+    })
+    .await
+    .respond()
+}
+
+#[get("{id}/smtp-relays")]
+async fn smtp_relays(id: Path<Uuid>, req: HttpRequest) -> HttpResult {
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::ApplicationSmtpRelayList)?;
+        let app_id = ApplicationRepository.find_owned_by_id(ctx.database(), *id, ctx.auth_id())?;
+        SmtpRelayRepository.list_by_application(ctx.database(), app_id)
+    })
+    .await
+    .respond()
+}
+
+#[put("{id}/smtp-relays")]
+async fn smtp_relays_assign(
+    id: Path<Uuid>,
+    form: Json<ApplicationSmtpRelaysForm>,
+    req: HttpRequest,
+) -> HttpResult {
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::ApplicationSmtpRelayAssign)?;
+        let app_id = ApplicationRepository.find_owned_by_id(ctx.database(), *id, ctx.auth_id())?;
+        SmtpRelayService.assign(ctx.database(), app_id, ctx.auth_id(), form.into_inner().ids)
+    })
+    .await
+    .respond()
+}
+
+#[get("{id}/templates")]
+async fn template_index(id: Path<Uuid>, q: Query<QueryParams>, req: HttpRequest) -> HttpResult {
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::MailTemplateList)?;
+        let app_id = ApplicationRepository.find_owned_by_id(ctx.database(), *id, ctx.auth_id())?;
+        MailTemplateRepository.list_by_application(ctx.database(), app_id, q.into_inner())
+    })
+    .await
+    .respond()
+}
+
+#[post("{id}/templates")]
+async fn template_store(
+    id: Path<Uuid>,
+    form: Json<MailTemplateCreateForm>,
+    req: HttpRequest,
+) -> HttpResult {
+    form.validate()?;
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::MailTemplateCreate)?;
+        let app_id = ApplicationRepository.find_owned_by_id(ctx.database(), *id, ctx.auth_id())?;
+        MailTemplateService.create(ctx.database(), app_id, ctx.auth_id(), form.into_inner())
+    })
+    .await
+    .respond()
+}
+
+#[get("{id}/templates/{name}")]
+async fn template_show(
+    path: Path<(Uuid, String)>,
+    q: Query<MailTemplateVersionParam>,
+    req: HttpRequest,
+) -> HttpResult {
+    let ctx = req.context();
+    let (id, name) = path.into_inner();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::MailTemplateRead)?;
+        let app_id = ApplicationRepository.find_owned_by_id(ctx.database(), id, ctx.auth_id())?;
+        MailTemplateService.find(ctx.database(), app_id, name, q.version)
+    })
+    .await
+    .respond()
+}
+
+#[get("{id}/templates/{name}/versions")]
+async fn template_versions(path: Path<(Uuid, String)>, req: HttpRequest) -> HttpResult {
+    let ctx = req.context();
+    let (id, name) = path.into_inner();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::MailTemplateRead)?;
+        let app_id = ApplicationRepository.find_owned_by_id(ctx.database(), id, ctx.auth_id())?;
+        MailTemplateRepository.list_versions(ctx.database(), app_id, name)
+    })
+    .await
+    .respond()
+}
 
-        let message = Box::new(match total_mails <= 1 {
-            true => format!("{} mail queued", total_mails),
-            false => format!("{} mails queued", total_mails),
-        });
+#[put("{id}/templates/{name}")]
+async fn template_update(
+    path: Path<(Uuid, String)>,
+    form: Json<MailTemplateUpdateForm>,
+    req: HttpRequest,
+) -> HttpResult {
+    form.validate()?;
+    let ctx = req.context();
+    let (id, name) = path.into_inner();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::MailTemplateUpdate)?;
+        let app_id = ApplicationRepository.find_owned_by_id(ctx.database(), id, ctx.auth_id())?;
+        MailTemplateService.update(
+            ctx.database(),
+            app_id,
+            ctx.auth_id(),
+            name,
+            form.into_inner(),
+        )
+    })
+    .await
+    .respond()
+}
 
-        Ok(AppMessage::SuccessMessageStr(Box::leak(message)))
+#[delete("{id}/templates/{name}")]
+async fn template_delete(path: Path<(Uuid, String)>, req: HttpRequest) -> HttpResult {
+    let ctx = req.context();
+    let (id, name) = path.into_inner();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::MailTemplateDelete)?;
+        let app_id = ApplicationRepository.find_owned_by_id(ctx.database(), id, ctx.auth_id())?;
+        match MailTemplateRepository.delete(ctx.database(), app_id, name)? {
+            0 => Err(AppMessage::EntityNotFound(String::from("mail template"))),
+            _ => Ok(AppMessage::SuccessMessageStr("mail template deleted")),
+        }
     })
     .await
     .respond()
@@ -128,6 +370,7 @@ async fn update(form: Json<ApplicationUpdateForm>, id: Path<Uuid>, req: HttpRequ
     let ctx = req.context();
     block(move || {
         ctx.verify_user_permission(AuthPermission::ApplicationList)?;
+        ApplicationService.verify_max_attachment_size(&ctx.app(), form.max_attachment_size)?;
         ApplicationService.update(ctx.database(), id.to_owned(), form.into_inner())
     })
     .await
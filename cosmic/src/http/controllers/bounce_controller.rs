@@ -0,0 +1,43 @@
+use actix_web::web::{block, Bytes, Data, ServiceConfig};
+use actix_web::{post, HttpRequest};
+
+use crate::app_state::AppState;
+use crate::enums::app_message::AppMessage;
+use crate::helpers::hmac::secure_compare;
+use crate::results::http_result::ActixBlockingResultResponder;
+use crate::results::HttpResult;
+use crate::services::bounce_service::BounceService;
+
+pub fn bounce_controller(cfg: &mut ServiceConfig) {
+    cfg.service(store);
+}
+
+/// Receives delivery status notifications piped from the bounce domain's mail server,
+/// the raw message is posted as the body along with the `X-Bounce-Token` header
+#[post("")]
+async fn store(body: Bytes, req: HttpRequest, app: Data<AppState>) -> HttpResult {
+    let token = req
+        .headers()
+        .get("X-Bounce-Token")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+
+    block(move || {
+        let authorized = match token {
+            Some(token) => app
+                .bounce_token
+                .as_ref()
+                .is_some_and(|expected| secure_compare(&token, expected)),
+            None => false,
+        };
+
+        if !authorized {
+            return Err(AppMessage::UnAuthorizedMessage("Invalid bounce token"));
+        }
+
+        let raw = String::from_utf8_lossy(&body).to_string();
+        BounceService.process(app.get_ref(), &raw)
+    })
+    .await
+    .respond()
+}
@@ -0,0 +1,62 @@
+use actix_web::web::{block, Bytes, Data, ServiceConfig};
+use actix_web::{post, HttpRequest};
+
+use crate::app_state::AppState;
+use crate::enums::app_message::AppMessage;
+use crate::models::mail::MailPayload;
+use crate::results::http_result::ActixBlockingResultResponder;
+use crate::results::HttpResult;
+use crate::services::app_key_service::AppKeyService;
+use crate::services::mail_service::MailService;
+
+pub fn mail_controller(cfg: &mut ServiceConfig) {
+    cfg.service(send);
+}
+
+/// Machine-to-machine mail submission, authenticated by the application's public key
+/// and the body's signature computed with the private key
+#[post("")]
+async fn send(body: Bytes, req: HttpRequest, app: Data<AppState>) -> HttpResult {
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+
+    let public_key = header("X-Mailer-Key");
+    let signature = header("X-Mailer-Signature");
+    let idempotency_key = header("Idempotency-Key");
+
+    block(move || {
+        let (public_key, signature) = match (public_key, signature) {
+            (Some(key), Some(signature)) => (key, signature),
+            _ => {
+                return Err(AppMessage::UnAuthorizedMessage(
+                    "X-Mailer-Key and X-Mailer-Signature headers are required",
+                ))
+            }
+        };
+
+        let body = String::from_utf8(body.to_vec())?;
+        let key = AppKeyService.authenticate(
+            app.database(),
+            public_key,
+            signature,
+            &body,
+            app.signature_tolerance,
+        )?;
+
+        let payload = serde_json::from_str::<MailPayload>(&body)
+            .map_err(|err| AppMessage::WarningMessage(format!("Invalid payload: {}", err)))?;
+        MailService.enqueue(
+            app.get_ref(),
+            key.application_id,
+            key.created_by,
+            payload.mails,
+            idempotency_key,
+        )
+    })
+    .await
+    .respond()
+}
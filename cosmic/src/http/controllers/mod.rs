@@ -1,7 +1,12 @@
 pub mod application_controller;
 pub mod auth_controller;
+pub mod bounce_controller;
+pub mod mail_controller;
 pub mod main_controller_guest;
 pub mod misc_controller;
 pub mod profile_controller;
 pub mod setting_controller;
+pub mod smtp_relay_controller;
+pub mod suppression_controller;
 pub mod system_controller;
+pub mod template_controller;
@@ -0,0 +1,77 @@
+use actix_web::web::{block, Json, Path, Query, ServiceConfig};
+use actix_web::{delete, get, post, put, HttpRequest};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::enums::auth_permission::AuthPermission;
+use crate::helpers::http::QueryParams;
+use crate::helpers::request::RequestHelper;
+use crate::models::smtp_relay::{SmtpRelayCreateForm, SmtpRelayUpdateForm};
+use crate::results::http_result::ActixBlockingResultResponder;
+use crate::results::HttpResult;
+use crate::services::smtp_relay_service::SmtpRelayService;
+
+pub fn smtp_relay_controller(cfg: &mut ServiceConfig) {
+    cfg.service(index);
+    cfg.service(store);
+    cfg.service(show);
+    cfg.service(update);
+    cfg.service(delete);
+}
+
+#[get("")]
+async fn index(q: Query<QueryParams>, req: HttpRequest) -> HttpResult {
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::SmtpRelayList)?;
+        SmtpRelayService.list(ctx.database(), ctx.auth_id(), q.into_inner())
+    })
+    .await
+    .respond()
+}
+
+#[post("")]
+async fn store(form: Json<SmtpRelayCreateForm>, req: HttpRequest) -> HttpResult {
+    form.validate()?;
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::SmtpRelayCreate)?;
+        SmtpRelayService.create(&ctx.app(), ctx.auth_id(), form.into_inner())
+    })
+    .await
+    .respond()
+}
+
+#[get("{id}")]
+async fn show(id: Path<Uuid>, req: HttpRequest) -> HttpResult {
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::SmtpRelayRead)?;
+        SmtpRelayService.find_accessible(ctx.database(), *id, ctx.auth_id())
+    })
+    .await
+    .respond()
+}
+
+#[put("{id}")]
+async fn update(id: Path<Uuid>, form: Json<SmtpRelayUpdateForm>, req: HttpRequest) -> HttpResult {
+    form.validate()?;
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::SmtpRelayUpdate)?;
+        SmtpRelayService.update(&ctx.app(), *id, ctx.auth_id(), form.into_inner())
+    })
+    .await
+    .respond()
+}
+
+#[delete("{id}")]
+async fn delete(id: Path<Uuid>, req: HttpRequest) -> HttpResult {
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::SmtpRelayDelete)?;
+        SmtpRelayService.delete(ctx.database(), *id, ctx.auth_id())
+    })
+    .await
+    .respond()
+}
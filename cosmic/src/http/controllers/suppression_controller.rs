@@ -0,0 +1,86 @@
+use actix_web::web::{block, Json, Path, Query, ServiceConfig};
+use actix_web::{delete, get, post, put, HttpRequest};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::enums::auth_permission::AuthPermission;
+use crate::helpers::http::QueryParams;
+use crate::helpers::request::RequestHelper;
+use crate::models::suppression::{
+    SuppressionCreateForm, SuppressionFilterParams, SuppressionUpdateForm,
+};
+use crate::repositories::suppression_repository::SuppressionRepository;
+use crate::results::http_result::ActixBlockingResultResponder;
+use crate::results::HttpResult;
+use crate::services::suppression_service::SuppressionService;
+
+pub fn suppression_controller(cfg: &mut ServiceConfig) {
+    cfg.service(index);
+    cfg.service(store);
+    cfg.service(show);
+    cfg.service(update);
+    cfg.service(delete);
+}
+
+#[get("")]
+async fn index(
+    q: Query<QueryParams>,
+    filter: Query<SuppressionFilterParams>,
+    req: HttpRequest,
+) -> HttpResult {
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::SuppressionList)?;
+        let app_id = filter.application_id;
+        SuppressionService.verify_access(ctx.database(), app_id, ctx.auth_id())?;
+        SuppressionRepository.list(ctx.database(), app_id, q.into_inner())
+    })
+    .await
+    .respond()
+}
+
+#[post("")]
+async fn store(form: Json<SuppressionCreateForm>, req: HttpRequest) -> HttpResult {
+    form.validate()?;
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::SuppressionCreate)?;
+        SuppressionService.create(ctx.database(), ctx.auth_id(), form.into_inner())
+    })
+    .await
+    .respond()
+}
+
+#[get("{id}")]
+async fn show(id: Path<Uuid>, req: HttpRequest) -> HttpResult {
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::SuppressionRead)?;
+        SuppressionService.find_accessible(ctx.database(), *id, ctx.auth_id())
+    })
+    .await
+    .respond()
+}
+
+#[put("{id}")]
+async fn update(id: Path<Uuid>, form: Json<SuppressionUpdateForm>, req: HttpRequest) -> HttpResult {
+    form.validate()?;
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::SuppressionUpdate)?;
+        SuppressionService.update(ctx.database(), *id, ctx.auth_id(), form.into_inner())
+    })
+    .await
+    .respond()
+}
+
+#[delete("{id}")]
+async fn delete(id: Path<Uuid>, req: HttpRequest) -> HttpResult {
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::SuppressionDelete)?;
+        SuppressionService.delete(ctx.database(), *id, ctx.auth_id())
+    })
+    .await
+    .respond()
+}
@@ -0,0 +1,49 @@
+use actix_web::web::{block, Json, ServiceConfig};
+use actix_web::{post, HttpRequest};
+
+use crate::enums::auth_permission::AuthPermission;
+use crate::helpers::request::RequestHelper;
+use crate::models::mail::MailBox;
+use crate::models::mail_template::MailTemplatePreviewForm;
+use crate::results::http_result::ActixBlockingResultResponder;
+use crate::results::HttpResult;
+use crate::services::mail_template_service::MailTemplateService;
+use crate::services::mailer_service::MailerService;
+
+pub fn template_controller(cfg: &mut ServiceConfig) {
+    cfg.service(preview);
+    cfg.service(test_send);
+}
+
+#[post("preview")]
+async fn preview(form: Json<MailTemplatePreviewForm>, req: HttpRequest) -> HttpResult {
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::MailTemplatePreview)?;
+        MailTemplateService.preview(ctx.app().as_ref(), form.into_inner())
+    })
+    .await
+    .respond()
+}
+
+/// Renders the template and mails the result to the authenticated user
+#[post("test-send")]
+async fn test_send(form: Json<MailTemplatePreviewForm>, req: HttpRequest) -> HttpResult {
+    let ctx = req.context();
+    block(move || {
+        ctx.verify_user_permission(AuthPermission::MailTemplateTestSend)?;
+        let rendered = MailTemplateService.preview(ctx.app().as_ref(), form.into_inner())?;
+
+        let user = ctx.auth_user();
+        MailerService::new(ctx.app())
+            .subject(rendered.subject.clone())
+            .receivers(vec![MailBox::new(&user.username, &user.email)])
+            .body(rendered.html.clone())
+            .text(rendered.text.clone())
+            .queue()?;
+
+        Ok(rendered)
+    })
+    .await
+    .respond()
+}
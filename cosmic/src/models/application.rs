@@ -1,6 +1,7 @@
 use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use validator::Validate;
 
 use super::super::schema::applications;
 
@@ -22,8 +23,17 @@ pub struct Application {
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
     pub deleted_at: Option<chrono::NaiveDateTime>,
+    pub max_attachment_size: i64,
+    pub rate_limit_per_second: Option<i32>,
+    pub rate_limit_per_minute: Option<i32>,
+    pub rate_limit_per_day: Option<i32>,
+    /// receivers, cc & bcc a single mail may address
+    pub max_recipients: Option<i32>,
 }
 
+/// total size in bytes of attachments a single mail may carry, unless configured otherwise
+pub const DEFAULT_MAX_ATTACHMENT_SIZE: i64 = 10 * 1024 * 1024;
+
 impl Application {
     pub fn transform_response(&mut self) -> Application {
         self.to_owned()
@@ -43,6 +53,7 @@ pub struct ApplicationCreateForm {
     pub sso_callback: String,
     pub webhook: String,
     pub description: String,
+    pub max_attachment_size: Option<i64>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -53,4 +64,18 @@ pub struct ApplicationUpdateForm {
     pub sso_callback: String,
     pub webhook: String,
     pub description: String,
+    pub max_attachment_size: Option<i64>,
+}
+
+/// absent limits are not enforced
+#[derive(Serialize, Deserialize, Validate)]
+pub struct ApplicationLimitsForm {
+    #[validate(range(min = 1))]
+    pub rate_limit_per_second: Option<i32>,
+    #[validate(range(min = 1))]
+    pub rate_limit_per_minute: Option<i32>,
+    #[validate(range(min = 1))]
+    pub rate_limit_per_day: Option<i32>,
+    #[validate(range(min = 1))]
+    pub max_recipients: Option<i32>,
 }
@@ -0,0 +1,28 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Delivery status notification (RFC 3464) reported back for a sent mail
+#[derive(Serialize, Clone, Debug)]
+pub struct DeliveryStatusReport {
+    /// recovered from the VERP return path, or from the returned Message-ID
+    pub mail_id: Option<Uuid>,
+    pub recipients: Vec<DeliveryStatusRecipient>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DeliveryStatusRecipient {
+    pub recipient: String,
+    /// one of (failed, delayed, delivered, relayed, expanded)
+    pub action: String,
+    /// enhanced status code, e.g. `5.1.1`
+    pub status: Option<String>,
+    pub diagnostic_code: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DeliveryStatusProcessed {
+    pub mail_id: Uuid,
+    pub status: String,
+    pub failed: Vec<String>,
+    pub delayed: Vec<String>,
+}
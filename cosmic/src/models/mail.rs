@@ -1,9 +1,16 @@
+use chrono::{Duration, NaiveDateTime};
 use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
 use serde::{Deserialize, Serialize};
 use strum_macros::{Display, EnumString};
 use uuid::Uuid;
 
-use crate::helpers::http::HttpHeaderItem;
+use crate::enums::app_message::AppMessage;
+use crate::helpers::http::date_from_unsafe_input;
+use crate::models::mail_address::MailAddress;
+use crate::models::mail_attachment::{MailAttachment, MailAttachmentData, MailAttachmentPayload};
+use crate::models::mail_error::{MailError, SmtpErrorDetail};
+use crate::models::mail_template::MailTemplateRef;
+use crate::models::webhook_delivery::MailWebhookEvent;
 
 use super::super::schema::mails;
 
@@ -28,6 +35,16 @@ pub struct Mail {
     pub next_retrial_at: Option<chrono::NaiveDateTime>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
+    pub reference: Option<String>,
+    pub smtp_response_code: Option<i16>,
+    pub smtp_response: Option<String>,
+    pub smtp_queue_id: Option<String>,
+    pub message_id: Option<String>,
+    pub text_message: Option<String>,
+    pub send_at: Option<chrono::NaiveDateTime>,
+    pub priority: String,
+    /// recipients dropped because they are suppressed, see `SuppressedRecipient`
+    pub suppressed_recipients: Option<serde_json::Value>,
 }
 
 #[derive(Clone, PartialEq, Display, Debug, EnumString)]
@@ -38,17 +55,85 @@ pub enum MailStatus {
     Retrying,
     Failed,
     Sent,
+    Scheduled,
+    Cancelled,
+    /// every recipient of the mail is suppressed
+    Suppressed,
+    /// every recipient of the mail was reported undeliverable after it had been sent
+    Bounced,
+}
+
+/// Mails of a higher priority are sent first, e.g. one-time codes go ahead of newsletters
+#[derive(Clone, Copy, PartialEq, Display, Debug, EnumString, Serialize, Deserialize, Default)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum MailPriority {
+    High,
+    #[default]
+    Normal,
+    Bulk,
+}
+
+#[derive(Serialize)]
+pub struct MailDetail {
+    #[serde(flatten)]
+    pub mail: Mail,
+    pub mail_addresses: Vec<MailAddress>,
+    pub mail_errors: Vec<MailError>,
+    pub mail_attachments: Vec<MailAttachment>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct MailFilterParams {
+    pub receiver: Option<String>,
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+}
+
+impl MailFilterParams {
+    pub fn get_date_from(&self) -> Result<Option<NaiveDateTime>, AppMessage> {
+        self.date_from
+            .as_ref()
+            .map(|date| date_from_unsafe_input(date, "date_from"))
+            .transpose()
+    }
+
+    /// end of the range is inclusive, so we move to the start of the next day
+    pub fn get_date_to(&self) -> Result<Option<NaiveDateTime>, AppMessage> {
+        self.date_to
+            .as_ref()
+            .map(|date| date_from_unsafe_input(date, "date_to").map(|d| d + Duration::days(1)))
+            .transpose()
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct MailData {
+    pub reference: Option<String>,
+    #[serde(default)]
     pub subject: String,
+    #[serde(default)]
     pub message: String,
+    /// plain-text alternative, derived from `message` when not provided
+    pub text: Option<String>,
+    /// name of the application's mail template to render subject & message from
+    pub template: Option<String>,
+    pub template_version: Option<i32>,
+    #[serde(default)]
+    pub variables: serde_json::Value,
     pub receiver: Vec<MailBox>,
     pub cc: Vec<MailBox>,
     pub bcc: Vec<MailBox>,
     pub reply_to: Vec<MailBox>,
     pub from: Option<MailBox>,
+    #[serde(default)]
+    pub attachments: Vec<MailAttachmentData>,
+    /// mail is held back until then, sent right away when absent or already due
+    pub send_at: Option<NaiveDateTime>,
+    /// resubmitting a mail with the same key returns the originally queued mail
+    pub idempotency_key: Option<String>,
+    #[serde(default)]
+    pub priority: MailPriority,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -64,16 +149,49 @@ pub struct ImpulseForwardablePayload {
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct MailQueueablePayload {
+    #[serde(default = "Uuid::new_v4")]
+    pub mail_id: Uuid,
+    pub reference: Option<String>,
     pub created_by: Uuid,
     pub application_id: Uuid,
 
     pub subject: String,
     pub message: String,
+    #[serde(default)]
+    pub text: Option<String>,
     pub receiver: Vec<MailBox>,
     pub cc: Vec<MailBox>,
     pub bcc: Vec<MailBox>,
     pub reply_to: Vec<MailBox>,
     pub from: Option<MailBox>,
+    #[serde(default)]
+    pub attachments: Vec<MailAttachmentPayload>,
+    #[serde(default)]
+    pub template: Option<MailTemplateRef>,
+    #[serde(default)]
+    pub send_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub priority: MailPriority,
+}
+
+#[derive(Deserialize)]
+pub struct MailScheduleForm {
+    pub send_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MailQueued {
+    pub mail_id: Option<Uuid>,
+    pub reference: Option<String>,
+    pub queued: bool,
+    pub error: Option<String>,
+}
+
+/// What an idempotency key is bound to, `result` stays empty while the request is in progress
+#[derive(Serialize, Deserialize, Clone)]
+pub struct IdempotencyRecord<T> {
+    pub request_hash: String,
+    pub result: Option<T>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -85,12 +203,28 @@ pub struct MailSaved {
     pub reply_to: Vec<MailBox>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct MailCallbackPayload {
-    pub reference: String,
-    pub status_code: u16,
-    pub response_body: String,
-    pub headers: Vec<HttpHeaderItem>,
+    pub application_id: Uuid,
+    /// delivery log entry, only available when the callback is being retried
+    pub webhook_delivery_id: Option<Uuid>,
+    pub event: MailWebhookEvent,
+}
+
+/// Item held in the retrying sorted set until it is due,
+/// the payload is then pushed as-is onto the named queue
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DelayedQueueItem {
+    pub queue: String,
+    pub payload: String,
+}
+
+/// List an item is parked in while a consumer handles it
+#[derive(Serialize, Deserialize, Clone)]
+pub struct InFlightQueue {
+    pub queue: String,
+    pub list: String,
+    pub worker_id: String,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -103,10 +237,26 @@ pub struct MailBox {
 pub struct MailSuccessResponse {
     pub saved_mail: MailSaved,
     pub response_body: String,
+    #[serde(default)]
+    pub response_code: Option<i16>,
+    #[serde(default)]
+    pub queue_id: Option<String>,
+    #[serde(default)]
+    pub message_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct MailFailureResponse {
     pub saved_mail: MailSaved,
     pub error_message: String,
+    #[serde(default)]
+    pub smtp: SmtpErrorDetail,
+}
+
+/// Outcome of throttling a mail before it is sent
+pub enum ThrottleDecision {
+    /// put back onto the delayed queue
+    Deferred,
+    /// may be sent, the recipient domains' sending slots must be released afterward
+    Permitted(Vec<String>),
 }
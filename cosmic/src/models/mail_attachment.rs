@@ -0,0 +1,37 @@
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Queryable, Selectable, Insertable, Clone, Serialize, Deserialize)]
+#[diesel(table_name = crate::schema::mail_attachments)]
+#[diesel(primary_key(mail_attachment_id))]
+pub struct MailAttachment {
+    pub mail_attachment_id: Uuid,
+    pub mail_id: Uuid,
+    pub file_upload_id: Option<Uuid>,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i32,
+    #[serde(skip)]
+    pub content: Vec<u8>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+/// Attachment as submitted by the client,
+/// either inline base64 `content` or a reference to an uploaded file
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MailAttachmentData {
+    pub file_name: Option<String>,
+    pub content_type: Option<String>,
+    pub content: Option<String>,
+    pub file_upload_id: Option<Uuid>,
+}
+
+/// Resolved attachment carried in the awaiting queue, `content` is base64 encoded
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MailAttachmentPayload {
+    pub file_upload_id: Option<Uuid>,
+    pub file_name: String,
+    pub content_type: String,
+    pub content: String,
+}
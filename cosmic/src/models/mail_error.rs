@@ -1,7 +1,8 @@
 use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Queryable, Selectable, Insertable)]
+#[derive(Queryable, Selectable, Insertable, Clone, Serialize, Deserialize)]
 #[diesel(table_name = crate::schema::mail_errors)]
 #[diesel(primary_key(mail_error_id))]
 pub struct MailError {
@@ -9,4 +10,15 @@ pub struct MailError {
     pub mail_id: Uuid,
     pub smtp_error: String,
     pub created_at: chrono::NaiveDateTime,
+    pub smtp_code: Option<i16>,
+    pub enhanced_code: Option<String>,
+    pub is_permanent: bool,
+}
+
+/// Parsed SMTP failure, permanent failures are not retried
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct SmtpErrorDetail {
+    pub smtp_code: Option<i16>,
+    pub enhanced_code: Option<String>,
+    pub permanent: bool,
 }
@@ -0,0 +1,84 @@
+use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use super::super::schema::mail_templates;
+
+#[derive(
+    Debug, Serialize, Deserialize, Insertable, Queryable, AsChangeset, Identifiable, Clone,
+)]
+#[diesel(table_name = mail_templates)]
+#[diesel(primary_key(mail_template_id))]
+pub struct MailTemplate {
+    pub mail_template_id: Uuid,
+    pub application_id: Uuid,
+    pub created_by: Uuid,
+    pub name: String,
+    pub version: i32,
+    pub subject: String,
+    pub html: String,
+    pub text: Option<String>,
+    pub is_latest: bool,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct MailTemplateCreateForm {
+    #[validate(length(min = 1, max = 150))]
+    pub name: String,
+
+    #[validate(length(min = 1, max = 250))]
+    pub subject: String,
+
+    #[validate(length(min = 1))]
+    pub html: String,
+
+    pub text: Option<String>,
+}
+
+/// Updating a template creates a new version, previous versions stay renderable
+#[derive(Deserialize, Validate)]
+pub struct MailTemplateUpdateForm {
+    #[validate(length(min = 1, max = 250))]
+    pub subject: String,
+
+    #[validate(length(min = 1))]
+    pub html: String,
+
+    pub text: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct MailTemplateVersionParam {
+    pub version: Option<i32>,
+}
+
+/// Template a queued mail should be rendered from, defaults to the latest version
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MailTemplateRef {
+    pub name: String,
+    pub version: Option<i32>,
+    #[serde(default)]
+    pub variables: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+pub struct MailTemplatePreviewForm {
+    /// bundled template under `resources/templates` (e.g. `otp`), used instead of `html`
+    pub template: Option<String>,
+    pub subject: Option<String>,
+    pub html: Option<String>,
+    pub text: Option<String>,
+    #[serde(default)]
+    pub variables: serde_json::Value,
+}
+
+#[derive(Serialize)]
+pub struct RenderedTemplate {
+    pub subject: String,
+    pub html: String,
+    pub text: Option<String>,
+}
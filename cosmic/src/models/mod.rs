@@ -4,22 +4,28 @@ pub mod announcement;
 pub mod app_key;
 pub mod application;
 pub mod auth_attempt;
+pub mod delivery_status;
 pub mod file_upload;
 pub mod mail;
 pub mod mail_address;
+pub mod mail_attachment;
 pub mod mail_error;
+pub mod mail_template;
 pub mod notification;
 pub mod password_reset;
 pub mod permission;
 pub mod personal_access_token;
 pub mod role;
 pub mod role_permission;
+pub mod smtp_relay;
+pub mod suppression;
 pub mod ui_menu;
 pub mod ui_menu_item;
 pub mod user;
 pub mod user_permission;
 pub mod user_role;
 pub mod user_ui_menu_item;
+pub mod webhook_delivery;
 
 // type alias to use in multiple places
 pub type DBPool = r2d2::Pool<ConnectionManager<PgConnection>>;
@@ -0,0 +1,109 @@
+use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use uuid::Uuid;
+use validator::Validate;
+
+use super::super::schema::{application_smtp_relays, smtp_relays};
+
+#[derive(
+    Debug, Serialize, Deserialize, Insertable, Queryable, AsChangeset, Identifiable, Clone,
+)]
+#[diesel(table_name = smtp_relays)]
+#[diesel(primary_key(smtp_relay_id))]
+pub struct SmtpRelay {
+    pub smtp_relay_id: Uuid,
+    pub created_by: Uuid,
+    pub name: String,
+    pub host: String,
+    pub port: i32,
+    pub encryption: String,
+    pub username: Option<String>,
+    #[serde(skip_serializing)]
+    pub password: Option<String>,
+    pub priority: i16,
+    pub weight: i16,
+    pub is_active: bool,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Insertable, Queryable, Clone)]
+#[diesel(table_name = application_smtp_relays)]
+#[diesel(primary_key(application_smtp_relay_id))]
+pub struct ApplicationSmtpRelay {
+    pub application_smtp_relay_id: Uuid,
+    pub application_id: Uuid,
+    pub smtp_relay_id: Uuid,
+    pub created_by: Uuid,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Clone, PartialEq, Display, Debug, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum SmtpRelayEncryption {
+    Local,
+    Basic,
+    Startls,
+    Tls,
+}
+
+/// Relays are tried by ascending `priority`, relays sharing a priority
+/// are picked at random proportionally to their `weight`
+#[derive(Deserialize, Validate)]
+pub struct SmtpRelayCreateForm {
+    #[validate(length(min = 1, max = 150))]
+    pub name: String,
+
+    #[validate(length(min = 1, max = 250))]
+    pub host: String,
+
+    #[validate(range(min = 1, max = 65535))]
+    pub port: i32,
+
+    pub encryption: String,
+
+    #[validate(length(max = 250))]
+    pub username: Option<String>,
+
+    pub password: Option<String>,
+
+    pub priority: Option<i16>,
+
+    #[validate(range(min = 1))]
+    pub weight: Option<i16>,
+}
+
+/// Same as the create form, the stored password is kept when none is given
+#[derive(Deserialize, Validate)]
+pub struct SmtpRelayUpdateForm {
+    #[validate(length(min = 1, max = 150))]
+    pub name: String,
+
+    #[validate(length(min = 1, max = 250))]
+    pub host: String,
+
+    #[validate(range(min = 1, max = 65535))]
+    pub port: i32,
+
+    pub encryption: String,
+
+    #[validate(length(max = 250))]
+    pub username: Option<String>,
+
+    pub password: Option<String>,
+
+    pub priority: Option<i16>,
+
+    #[validate(range(min = 1))]
+    pub weight: Option<i16>,
+
+    pub is_active: Option<bool>,
+}
+
+/// Replaces the relays an application sends through, in no particular order
+#[derive(Deserialize)]
+pub struct ApplicationSmtpRelaysForm {
+    pub ids: Vec<Uuid>,
+}
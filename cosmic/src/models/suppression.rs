@@ -0,0 +1,78 @@
+use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use uuid::Uuid;
+use validator::Validate;
+
+use super::super::schema::suppressions;
+
+/// Address mails are no longer sent to, for a single application or,
+/// when `application_id` is absent, for every application
+#[derive(
+    Debug, Serialize, Deserialize, Insertable, Queryable, AsChangeset, Identifiable, Clone,
+)]
+#[diesel(table_name = suppressions)]
+#[diesel(primary_key(suppression_id))]
+pub struct Suppression {
+    pub suppression_id: Uuid,
+    pub application_id: Option<Uuid>,
+    pub created_by: Option<Uuid>,
+    pub email: String,
+    pub reason: String,
+    pub source: String,
+    pub description: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Clone, PartialEq, Display, Debug, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum SuppressionReason {
+    HardBounce,
+    Complaint,
+    Unsubscribe,
+    Manual,
+}
+
+/// Where the suppression came from
+#[derive(Clone, PartialEq, Display, Debug, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum SuppressionSource {
+    Api,
+    Smtp,
+    Dsn,
+}
+
+/// Recipient dropped from a mail, recorded on the mail
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SuppressedRecipient {
+    pub email: String,
+    pub reason: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct SuppressionCreateForm {
+    pub application_id: Option<Uuid>,
+
+    #[validate(email, length(max = 250))]
+    pub email: String,
+
+    /// one of (hard_bounce, complaint, unsubscribe, manual)
+    pub reason: String,
+
+    #[validate(length(max = 1000))]
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct SuppressionUpdateForm {
+    pub reason: String,
+
+    #[validate(length(max = 1000))]
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SuppressionFilterParams {
+    pub application_id: Option<Uuid>,
+}
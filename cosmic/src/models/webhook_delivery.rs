@@ -0,0 +1,62 @@
+use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use uuid::Uuid;
+
+use super::super::schema::webhook_deliveries;
+
+#[derive(
+    Debug, Serialize, Deserialize, Insertable, Queryable, AsChangeset, Identifiable, Clone,
+)]
+#[diesel(table_name = webhook_deliveries)]
+#[diesel(primary_key(webhook_delivery_id))]
+pub struct WebhookDelivery {
+    pub webhook_delivery_id: Uuid,
+    pub application_id: Uuid,
+    pub mail_id: Uuid,
+    pub event: String,
+    pub url: String,
+    pub payload: String,
+    pub attempts: i16,
+    pub response_status: Option<i16>,
+    pub response_body: Option<String>,
+    pub status: String,
+    pub next_attempt_at: Option<chrono::NaiveDateTime>,
+    pub delivered_at: Option<chrono::NaiveDateTime>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Clone, PartialEq, Display, Debug, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    Retrying,
+    Failed,
+}
+
+#[derive(Clone, PartialEq, Display, Debug, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum WebhookEvent {
+    Queued,
+    Sent,
+    Retrying,
+    Failed,
+    Scheduled,
+    Cancelled,
+    Suppressed,
+    Bounced,
+}
+
+/// Body posted to the application's webhook url
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MailWebhookEvent {
+    pub event: String,
+    pub mail_id: Uuid,
+    pub reference: Option<String>,
+    pub status: String,
+    pub trials: i16,
+    pub error: Option<String>,
+    pub occurred_at: chrono::NaiveDateTime,
+}
@@ -51,4 +51,13 @@ impl AppKeyRepository {
             .first::<AppKey>(&mut pool.conn())
             .required("application key")
     }
+
+    pub fn find_active_by_public_key(&mut self, pool: &DBPool, key: String) -> AppResult<AppKey> {
+        app_keys::table
+            .filter(app_keys::public_key.eq(key))
+            .filter(app_keys::status.eq(String::from("active")))
+            .filter(app_keys::deleted_at.is_null())
+            .first::<AppKey>(&mut pool.conn())
+            .required("application key")
+    }
 }
@@ -10,7 +10,8 @@ use crate::helpers::http::QueryParams;
 use crate::helpers::time::current_timestamp;
 use crate::helpers::DBPool;
 use crate::models::application::{
-    Application, ApplicationCreateForm, ApplicationStatus, ApplicationUpdateForm,
+    Application, ApplicationCreateForm, ApplicationLimitsForm, ApplicationStatus,
+    ApplicationUpdateForm, DEFAULT_MAX_ATTACHMENT_SIZE,
 };
 use crate::results::app_result::FormatAppResult;
 use crate::results::{AppPaginationResult, AppResult};
@@ -58,6 +59,13 @@ impl ApplicationRepository {
                 created_at: current_timestamp(),
                 updated_at: current_timestamp(),
                 deleted_at: None,
+                max_attachment_size: data
+                    .max_attachment_size
+                    .unwrap_or(DEFAULT_MAX_ATTACHMENT_SIZE),
+                rate_limit_per_second: None,
+                rate_limit_per_minute: None,
+                rate_limit_per_day: None,
+                max_recipients: None,
             })
             .get_result::<Application>(&mut pool.conn())
             .into_app_result()
@@ -75,10 +83,33 @@ impl ApplicationRepository {
         app.url = form.url;
         app.webhook = form.webhook;
         app.description = form.description;
+        if let Some(size) = form.max_attachment_size {
+            app.max_attachment_size = size;
+        }
+
         app.save_changes::<Application>(&mut pool.conn())
             .into_app_result()
     }
 
+    pub fn update_limits(
+        &mut self,
+        pool: &DBPool,
+        id: Uuid,
+        form: ApplicationLimitsForm,
+    ) -> AppResult<Application> {
+        let app = self.find_by_id(pool, id)?;
+        diesel::update(applications::table.find(app.application_id))
+            .set((
+                applications::rate_limit_per_second.eq(form.rate_limit_per_second),
+                applications::rate_limit_per_minute.eq(form.rate_limit_per_minute),
+                applications::rate_limit_per_day.eq(form.rate_limit_per_day),
+                applications::max_recipients.eq(form.max_recipients),
+                applications::updated_at.eq(current_timestamp()),
+            ))
+            .get_result::<Application>(&mut pool.conn())
+            .into_app_result()
+    }
+
     pub fn delete(&mut self, pool: &DBPool, id: Uuid) -> AppResult<Application> {
         let mut app = self.find_by_id(pool, id)?;
         app.deleted_at = Some(current_timestamp());
@@ -37,6 +37,14 @@ impl MailAddressRepository {
         })
     }
 
+    pub fn list_by_mail_id(&mut self, pool: &DBPool, mail_id: Uuid) -> AppResult<Vec<MailAddress>> {
+        mail_addresses::table
+            .filter(mail_addresses::mail_id.eq(mail_id))
+            .order_by(mail_addresses::created_at.asc())
+            .get_results::<MailAddress>(get_db_conn(pool).deref_mut())
+            .into_app_result()
+    }
+
     pub fn create(
         &mut self,
         pool: &DBPool,
@@ -0,0 +1,54 @@
+use std::ops::DerefMut;
+
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::helpers::get_db_conn;
+use crate::helpers::time::current_timestamp;
+use crate::models::mail_attachment::MailAttachment;
+use crate::models::DBPool;
+use crate::results::app_result::FormatAppResult;
+use crate::results::AppResult;
+use crate::schema::mail_attachments;
+
+pub struct MailAttachmentRepository;
+
+impl MailAttachmentRepository {
+    pub fn list_by_mail_id(
+        &mut self,
+        pool: &DBPool,
+        mail_id: Uuid,
+    ) -> AppResult<Vec<MailAttachment>> {
+        mail_attachments::table
+            .filter(mail_attachments::mail_id.eq(mail_id))
+            .order_by(mail_attachments::created_at.asc())
+            .get_results::<MailAttachment>(get_db_conn(pool).deref_mut())
+            .into_app_result()
+    }
+
+    pub fn create(
+        &mut self,
+        pool: &DBPool,
+        mail_id: Uuid,
+        file_upload_id: Option<Uuid>,
+        file_name: String,
+        content_type: String,
+        content: Vec<u8>,
+    ) -> AppResult<MailAttachment> {
+        let model = MailAttachment {
+            mail_attachment_id: Uuid::new_v4(),
+            mail_id,
+            file_upload_id,
+            file_name,
+            content_type,
+            size: content.len() as i32,
+            content,
+            created_at: current_timestamp(),
+        };
+
+        diesel::insert_into(mail_attachments::dsl::mail_attachments)
+            .values(model)
+            .get_result::<MailAttachment>(get_db_conn(pool).deref_mut())
+            .into_app_result()
+    }
+}
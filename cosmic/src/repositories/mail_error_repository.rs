@@ -2,7 +2,7 @@ use crate::helpers::db_pagination::{PageData, Paginate};
 use crate::helpers::get_db_conn;
 use crate::helpers::http::QueryParams;
 use crate::helpers::time::current_timestamp;
-use crate::models::mail_error::MailError;
+use crate::models::mail_error::{MailError, SmtpErrorDetail};
 use crate::models::DBPool;
 use crate::results::app_result::FormatAppResult;
 use crate::results::AppResult;
@@ -29,17 +29,29 @@ impl MailErrorRepository {
             .into_app_result()
     }
 
+    pub fn list_by_mail_id(&mut self, pool: &DBPool, mail_id: Uuid) -> AppResult<Vec<MailError>> {
+        mail_errors::table
+            .filter(mail_errors::mail_id.eq(mail_id))
+            .order_by(mail_errors::created_at.asc())
+            .get_results::<MailError>(get_db_conn(pool).deref_mut())
+            .into_app_result()
+    }
+
     pub fn create(
         &mut self,
         pool: &DBPool,
         mail_id: Uuid,
         smtp_error: String,
+        detail: SmtpErrorDetail,
     ) -> AppResult<MailError> {
         let model = MailError {
             mail_id,
             smtp_error,
             mail_error_id: Uuid::new_v4(),
             created_at: current_timestamp(),
+            smtp_code: detail.smtp_code,
+            enhanced_code: detail.enhanced_code,
+            is_permanent: detail.permanent,
         };
 
         diesel::insert_into(mail_errors::dsl::mail_errors)
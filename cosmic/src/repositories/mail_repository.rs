@@ -1,24 +1,75 @@
 use std::ops::DerefMut;
 
-use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use diesel::{ExpressionMethods, PgTextExpressionMethods, QueryDsl, RunQueryDsl};
 use uuid::Uuid;
 
-use crate::helpers::db::OptionalResult;
+use crate::helpers::db::{DatabaseConnectionHelper, OptionalResult};
+use crate::helpers::db_pagination::Paginate;
 use crate::helpers::get_db_conn;
+use crate::helpers::html::html_to_text;
+use crate::helpers::http::QueryParams;
 use crate::helpers::time::current_timestamp;
-use crate::models::mail::{Mail, MailQueueablePayload, MailStatus};
+use crate::models::mail::{Mail, MailFilterParams, MailQueueablePayload, MailStatus};
 use crate::models::DBPool;
 use crate::results::app_result::FormatAppResult;
-use crate::results::AppResult;
-use crate::schema::mails;
+use crate::results::{AppPaginationResult, AppResult};
+use crate::schema::{mail_addresses, mails};
 
 pub struct MailRepository;
 
 impl MailRepository {
+    pub fn list_by_application(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        q: QueryParams,
+        filter: MailFilterParams,
+    ) -> AppPaginationResult<Mail> {
+        let mut query = mails::table
+            .filter(mails::application_id.eq(app_id))
+            .filter(mails::subject.ilike(q.get_search_query_like()))
+            .into_boxed();
+
+        if let Some(status) = q.status.clone() {
+            query = query.filter(mails::status.eq(status));
+        }
+
+        if let Some(receiver) = filter.receiver.clone() {
+            let sq_receiver_mails = mail_addresses::table
+                .select(mail_addresses::mail_id)
+                .filter(mail_addresses::email.ilike(format!("%{}%", receiver)));
+
+            query = query.filter(mails::mail_id.eq_any(sq_receiver_mails));
+        }
+
+        if let Some(date_from) = filter.get_date_from()? {
+            query = query.filter(mails::created_at.ge(date_from));
+        }
+
+        if let Some(date_to) = filter.get_date_to()? {
+            query = query.filter(mails::created_at.lt(date_to));
+        }
+
+        query
+            .order_by(mails::created_at.desc())
+            .paginate(q.get_page())
+            .per_page(q.get_per_page())
+            .load_and_count_pages::<Mail>(&mut pool.conn())
+            .into_app_result()
+    }
+
     pub fn create(&mut self, pool: &DBPool, payload: MailQueueablePayload) -> AppResult<Mail> {
         let from = payload.from.unwrap();
+        let text_message = payload
+            .text
+            .unwrap_or_else(|| html_to_text(&payload.message));
+        let status = match payload.send_at {
+            Some(send_at) if send_at > current_timestamp() => MailStatus::Scheduled,
+            _ => MailStatus::Awaiting,
+        };
+
         let model = Mail {
-            mail_id: Uuid::new_v4(),
+            mail_id: payload.mail_id,
             application_id: payload.application_id,
             subject: payload.subject,
             message: payload.message,
@@ -27,12 +78,21 @@ impl MailRepository {
             reply_to_name: None,
             created_by: payload.created_by,
             trials: 0,
-            status: MailStatus::Awaiting.to_string(),
+            status: status.to_string(),
             sent_at: None,
             next_retrial_at: None,
             created_at: current_timestamp(),
             updated_at: current_timestamp(),
             reply_to_email: None,
+            reference: payload.reference,
+            smtp_response_code: None,
+            smtp_response: None,
+            smtp_queue_id: None,
+            message_id: None,
+            text_message: Some(text_message),
+            send_at: payload.send_at,
+            priority: payload.priority.to_string(),
+            suppressed_recipients: None,
         };
 
         diesel::insert_into(mails::dsl::mails)
@@ -47,4 +107,24 @@ impl MailRepository {
             .first::<Mail>(get_db_conn(pool).deref_mut())
             .required("mail")
     }
+
+    pub fn find_optional(&mut self, pool: &DBPool, id: Uuid) -> AppResult<Option<Mail>> {
+        mails::table
+            .filter(mails::mail_id.eq(id))
+            .first::<Mail>(&mut pool.conn())
+            .optional()
+    }
+
+    pub fn find_by_application(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        id: Uuid,
+    ) -> AppResult<Mail> {
+        mails::table
+            .filter(mails::application_id.eq(app_id))
+            .filter(mails::mail_id.eq(id))
+            .first::<Mail>(&mut pool.conn())
+            .required("mail")
+    }
 }
@@ -0,0 +1,173 @@
+use diesel::{Connection, ExpressionMethods, PgTextExpressionMethods, QueryDsl, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::helpers::db::{DatabaseConnectionHelper, OptionalResult};
+use crate::helpers::db_pagination::Paginate;
+use crate::helpers::http::QueryParams;
+use crate::helpers::time::current_timestamp;
+use crate::helpers::DBPool;
+use crate::models::mail_template::{MailTemplate, MailTemplateCreateForm};
+use crate::results::app_result::FormatAppResult;
+use crate::results::{AppPaginationResult, AppResult};
+use crate::schema::mail_templates;
+
+pub struct MailTemplateRepository;
+
+impl MailTemplateRepository {
+    pub fn list_by_application(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        q: QueryParams,
+    ) -> AppPaginationResult<MailTemplate> {
+        mail_templates::table
+            .filter(mail_templates::application_id.eq(app_id))
+            .filter(mail_templates::is_latest.eq(true))
+            .filter(mail_templates::deleted_at.is_null())
+            .filter(mail_templates::name.ilike(q.get_search_query_like()))
+            .order_by(mail_templates::name.asc())
+            .paginate(q.get_page())
+            .per_page(q.get_per_page())
+            .load_and_count_pages::<MailTemplate>(&mut pool.conn())
+            .into_app_result()
+    }
+
+    pub fn list_versions(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        name: String,
+    ) -> AppResult<Vec<MailTemplate>> {
+        mail_templates::table
+            .filter(mail_templates::application_id.eq(app_id))
+            .filter(mail_templates::name.eq(name))
+            .filter(mail_templates::deleted_at.is_null())
+            .order_by(mail_templates::version.desc())
+            .get_results::<MailTemplate>(&mut pool.conn())
+            .into_app_result()
+    }
+
+    pub fn create(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        created_by: Uuid,
+        version: i32,
+        form: MailTemplateCreateForm,
+    ) -> AppResult<MailTemplate> {
+        diesel::insert_into(mail_templates::dsl::mail_templates)
+            .values(Self::make(app_id, created_by, version, form))
+            .get_result::<MailTemplate>(&mut pool.conn())
+            .into_app_result()
+    }
+
+    /// Marks the latest version as outdated & inserts the next one within a single transaction,
+    /// so the template is never left without a latest version
+    pub fn create_next_version(
+        &mut self,
+        pool: &DBPool,
+        latest: MailTemplate,
+        created_by: Uuid,
+        form: MailTemplateCreateForm,
+    ) -> AppResult<MailTemplate> {
+        let template = Self::make(latest.application_id, created_by, latest.version + 1, form);
+        pool.conn()
+            .transaction::<_, diesel::result::Error, _>(|conn| {
+                diesel::update(mail_templates::table)
+                    .filter(mail_templates::mail_template_id.eq(latest.mail_template_id))
+                    .set(mail_templates::is_latest.eq(false))
+                    .execute(conn)?;
+
+                diesel::insert_into(mail_templates::table)
+                    .values(template)
+                    .get_result::<MailTemplate>(conn)
+            })
+            .into_app_result()
+    }
+
+    fn make(
+        app_id: Uuid,
+        created_by: Uuid,
+        version: i32,
+        form: MailTemplateCreateForm,
+    ) -> MailTemplate {
+        MailTemplate {
+            mail_template_id: Uuid::new_v4(),
+            application_id: app_id,
+            created_by,
+            name: form.name,
+            version,
+            subject: form.subject,
+            html: form.html,
+            text: form.text,
+            is_latest: true,
+            created_at: current_timestamp(),
+            updated_at: current_timestamp(),
+            deleted_at: None,
+        }
+    }
+
+    pub fn find_latest(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        name: String,
+    ) -> AppResult<MailTemplate> {
+        mail_templates::table
+            .filter(mail_templates::application_id.eq(app_id))
+            .filter(mail_templates::name.eq(name))
+            .filter(mail_templates::is_latest.eq(true))
+            .filter(mail_templates::deleted_at.is_null())
+            .first::<MailTemplate>(&mut pool.conn())
+            .required("mail template")
+    }
+
+    pub fn find_version(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        name: String,
+        version: i32,
+    ) -> AppResult<MailTemplate> {
+        mail_templates::table
+            .filter(mail_templates::application_id.eq(app_id))
+            .filter(mail_templates::name.eq(name))
+            .filter(mail_templates::version.eq(version))
+            .filter(mail_templates::deleted_at.is_null())
+            .first::<MailTemplate>(&mut pool.conn())
+            .required("mail template")
+    }
+
+    pub fn exists(&mut self, pool: &DBPool, app_id: Uuid, name: String) -> AppResult<bool> {
+        mail_templates::table
+            .filter(mail_templates::application_id.eq(app_id))
+            .filter(mail_templates::name.eq(name))
+            .filter(mail_templates::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(&mut pool.conn())
+            .map(|count| count > 0)
+            .into_app_result()
+    }
+
+    /// Highest version ever used for the name, deleted versions included
+    pub fn max_version(&mut self, pool: &DBPool, app_id: Uuid, name: String) -> AppResult<i32> {
+        mail_templates::table
+            .filter(mail_templates::application_id.eq(app_id))
+            .filter(mail_templates::name.eq(name))
+            .select(diesel::dsl::max(mail_templates::version))
+            .get_result::<Option<i32>>(&mut pool.conn())
+            .map(|version| version.unwrap_or(0))
+            .into_app_result()
+    }
+
+    /// Deletes every version of the template
+    pub fn delete(&mut self, pool: &DBPool, app_id: Uuid, name: String) -> AppResult<usize> {
+        diesel::update(mail_templates::table)
+            .filter(mail_templates::application_id.eq(app_id))
+            .filter(mail_templates::name.eq(name))
+            .filter(mail_templates::deleted_at.is_null())
+            .set(mail_templates::deleted_at.eq(Some(current_timestamp())))
+            .execute(&mut pool.conn())
+            .into_app_result()
+    }
+}
@@ -4,17 +4,22 @@ pub mod application_repository;
 pub mod auth_attempt_repository;
 pub mod file_upload_repository;
 pub mod mail_address_repository;
+pub mod mail_attachment_repository;
 pub mod mail_error_repository;
 pub mod mail_repository;
+pub mod mail_template_repository;
 pub mod notification_repository;
 pub mod password_reset_repository;
 pub mod permission_repository;
 pub mod personal_access_token_repository;
 pub mod role_permission_repository;
 pub mod role_repository;
+pub mod smtp_relay_repository;
+pub mod suppression_repository;
 pub mod ui_menu_item_repository;
 pub mod ui_menu_repository;
 pub mod user_permission_repository;
 pub mod user_repository;
 pub mod user_role_repository;
 pub mod user_ui_menu_item_repository;
+pub mod webhook_delivery_repository;
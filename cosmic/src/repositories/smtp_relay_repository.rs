@@ -0,0 +1,153 @@
+use diesel::{
+    BoolExpressionMethods, Connection, ExpressionMethods, PgTextExpressionMethods, QueryDsl,
+    RunQueryDsl, SaveChangesDsl,
+};
+use uuid::Uuid;
+
+use crate::helpers::db::{DatabaseConnectionHelper, OptionalResult};
+use crate::helpers::db_pagination::Paginate;
+use crate::helpers::http::QueryParams;
+use crate::helpers::time::current_timestamp;
+use crate::helpers::DBPool;
+use crate::models::smtp_relay::{ApplicationSmtpRelay, SmtpRelay, SmtpRelayCreateForm};
+use crate::results::app_result::FormatAppResult;
+use crate::results::{AppPaginationResult, AppResult};
+use crate::schema::{application_smtp_relays, smtp_relays};
+
+pub struct SmtpRelayRepository;
+
+impl SmtpRelayRepository {
+    /// Relays created by `created_by`, every relay when absent
+    pub fn list(
+        &mut self,
+        pool: &DBPool,
+        created_by: Option<Uuid>,
+        q: QueryParams,
+    ) -> AppPaginationResult<SmtpRelay> {
+        let mut query = smtp_relays::table
+            .filter(
+                smtp_relays::name
+                    .ilike(q.get_search_query_like())
+                    .or(smtp_relays::host.ilike(q.get_search_query_like())),
+            )
+            .filter(smtp_relays::deleted_at.is_null())
+            .into_boxed();
+
+        if let Some(created_by) = created_by {
+            query = query.filter(smtp_relays::created_by.eq(created_by));
+        }
+
+        query
+            .order_by(smtp_relays::priority.asc())
+            .paginate(q.get_page())
+            .per_page(q.get_per_page())
+            .load_and_count_pages::<SmtpRelay>(&mut pool.conn())
+            .into_app_result()
+    }
+
+    pub fn create(
+        &mut self,
+        pool: &DBPool,
+        created_by: Uuid,
+        form: SmtpRelayCreateForm,
+    ) -> AppResult<SmtpRelay> {
+        diesel::insert_into(smtp_relays::dsl::smtp_relays)
+            .values(SmtpRelay {
+                smtp_relay_id: Uuid::new_v4(),
+                created_by,
+                name: form.name,
+                host: form.host,
+                port: form.port,
+                encryption: form.encryption,
+                username: form.username,
+                password: form.password,
+                priority: form.priority.unwrap_or(0),
+                weight: form.weight.unwrap_or(1),
+                is_active: true,
+                created_at: current_timestamp(),
+                updated_at: current_timestamp(),
+                deleted_at: None,
+            })
+            .get_result::<SmtpRelay>(&mut pool.conn())
+            .into_app_result()
+    }
+
+    pub fn find_by_id(&mut self, pool: &DBPool, id: Uuid) -> AppResult<SmtpRelay> {
+        smtp_relays::table
+            .filter(smtp_relays::smtp_relay_id.eq(id))
+            .filter(smtp_relays::deleted_at.is_null())
+            .first::<SmtpRelay>(&mut pool.conn())
+            .required("smtp relay")
+    }
+
+    pub fn delete(&mut self, pool: &DBPool, id: Uuid) -> AppResult<SmtpRelay> {
+        let mut relay = self.find_by_id(pool, id)?;
+        relay.deleted_at = Some(current_timestamp());
+        relay
+            .save_changes::<SmtpRelay>(&mut pool.conn())
+            .into_app_result()
+    }
+
+    pub fn list_by_application(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+    ) -> AppResult<Vec<SmtpRelay>> {
+        smtp_relays::table
+            .inner_join(application_smtp_relays::table)
+            .filter(application_smtp_relays::application_id.eq(app_id))
+            .filter(smtp_relays::deleted_at.is_null())
+            .order_by(smtp_relays::priority.asc())
+            .select(smtp_relays::all_columns)
+            .get_results::<SmtpRelay>(&mut pool.conn())
+            .into_app_result()
+    }
+
+    pub fn list_active_by_application(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+    ) -> AppResult<Vec<SmtpRelay>> {
+        smtp_relays::table
+            .inner_join(application_smtp_relays::table)
+            .filter(application_smtp_relays::application_id.eq(app_id))
+            .filter(smtp_relays::is_active.eq(true))
+            .filter(smtp_relays::deleted_at.is_null())
+            .order_by(smtp_relays::priority.asc())
+            .select(smtp_relays::all_columns)
+            .get_results::<SmtpRelay>(&mut pool.conn())
+            .into_app_result()
+    }
+
+    /// Replaces the application's relays within a single transaction
+    pub fn assign(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        created_by: Uuid,
+        ids: Vec<Uuid>,
+    ) -> AppResult<Vec<ApplicationSmtpRelay>> {
+        pool.conn()
+            .transaction::<_, diesel::result::Error, _>(|conn| {
+                diesel::delete(application_smtp_relays::table)
+                    .filter(application_smtp_relays::application_id.eq(app_id))
+                    .execute(conn)?;
+
+                let rows: Vec<ApplicationSmtpRelay> = ids
+                    .into_iter()
+                    .map(|smtp_relay_id| ApplicationSmtpRelay {
+                        application_smtp_relay_id: Uuid::new_v4(),
+                        application_id: app_id,
+                        smtp_relay_id,
+                        created_by,
+                        created_at: current_timestamp(),
+                    })
+                    .collect();
+
+                diesel::insert_into(application_smtp_relays::table)
+                    .values(rows)
+                    .get_results::<ApplicationSmtpRelay>(conn)
+            })
+            .into_app_result()
+    }
+}
@@ -0,0 +1,99 @@
+use diesel::{
+    BoolExpressionMethods, ExpressionMethods, PgTextExpressionMethods, QueryDsl, RunQueryDsl,
+};
+use uuid::Uuid;
+
+use crate::helpers::db::{DatabaseConnectionHelper, OptionalResult};
+use crate::helpers::db_pagination::Paginate;
+use crate::helpers::http::QueryParams;
+use crate::helpers::time::current_timestamp;
+use crate::helpers::DBPool;
+use crate::models::suppression::{Suppression, SuppressionCreateForm, SuppressionSource};
+use crate::results::app_result::FormatAppResult;
+use crate::results::{AppPaginationResult, AppResult};
+use crate::schema::suppressions;
+
+pub struct SuppressionRepository;
+
+impl SuppressionRepository {
+    /// Suppressions of the application, or the global ones when no application is given
+    pub fn list(
+        &mut self,
+        pool: &DBPool,
+        app_id: Option<Uuid>,
+        q: QueryParams,
+    ) -> AppPaginationResult<Suppression> {
+        let mut query = suppressions::table
+            .filter(suppressions::email.ilike(q.get_search_query_like()))
+            .into_boxed();
+
+        query = match app_id {
+            Some(app_id) => query.filter(suppressions::application_id.eq(app_id)),
+            None => query.filter(suppressions::application_id.is_null()),
+        };
+
+        query
+            .order_by(suppressions::created_at.desc())
+            .paginate(q.get_page())
+            .per_page(q.get_per_page())
+            .load_and_count_pages::<Suppression>(&mut pool.conn())
+            .into_app_result()
+    }
+
+    /// Inserts the suppression unless the address is already suppressed for the same scope
+    pub fn create(
+        &mut self,
+        pool: &DBPool,
+        created_by: Option<Uuid>,
+        source: SuppressionSource,
+        form: SuppressionCreateForm,
+    ) -> AppResult<Option<Suppression>> {
+        diesel::insert_into(suppressions::dsl::suppressions)
+            .values(Suppression {
+                suppression_id: Uuid::new_v4(),
+                application_id: form.application_id,
+                created_by,
+                email: form.email.to_lowercase(),
+                reason: form.reason,
+                source: source.to_string(),
+                description: form.description,
+                created_at: current_timestamp(),
+                updated_at: current_timestamp(),
+            })
+            .on_conflict_do_nothing()
+            .get_result::<Suppression>(&mut pool.conn())
+            .optional()
+    }
+
+    pub fn find_by_id(&mut self, pool: &DBPool, id: Uuid) -> AppResult<Suppression> {
+        suppressions::table
+            .filter(suppressions::suppression_id.eq(id))
+            .first::<Suppression>(&mut pool.conn())
+            .required("suppression")
+    }
+
+    /// Suppressions, global or of the application, covering any of the addresses
+    pub fn find_by_emails(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        emails: Vec<String>,
+    ) -> AppResult<Vec<Suppression>> {
+        let emails: Vec<String> = emails.iter().map(|email| email.to_lowercase()).collect();
+        suppressions::table
+            .filter(
+                suppressions::application_id
+                    .is_null()
+                    .or(suppressions::application_id.eq(app_id)),
+            )
+            .filter(suppressions::email.eq_any(emails))
+            .get_results::<Suppression>(&mut pool.conn())
+            .into_app_result()
+    }
+
+    pub fn delete(&mut self, pool: &DBPool, id: Uuid) -> AppResult<Suppression> {
+        diesel::delete(suppressions::table.find(id))
+            .get_result::<Suppression>(&mut pool.conn())
+            .required("suppression")
+    }
+}
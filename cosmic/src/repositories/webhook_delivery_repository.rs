@@ -0,0 +1,76 @@
+use diesel::{ExpressionMethods, PgTextExpressionMethods, QueryDsl, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::helpers::db::{DatabaseConnectionHelper, OptionalResult};
+use crate::helpers::db_pagination::Paginate;
+use crate::helpers::http::QueryParams;
+use crate::helpers::time::current_timestamp;
+use crate::helpers::DBPool;
+use crate::models::webhook_delivery::{WebhookDelivery, WebhookDeliveryStatus};
+use crate::results::app_result::FormatAppResult;
+use crate::results::{AppPaginationResult, AppResult};
+use crate::schema::webhook_deliveries;
+
+pub struct WebhookDeliveryRepository;
+
+impl WebhookDeliveryRepository {
+    pub fn list_by_application(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        q: QueryParams,
+    ) -> AppPaginationResult<WebhookDelivery> {
+        let mut query = webhook_deliveries::table
+            .filter(webhook_deliveries::application_id.eq(app_id))
+            .filter(webhook_deliveries::event.ilike(q.get_search_query_like()))
+            .into_boxed();
+
+        if let Some(status) = q.status.clone() {
+            query = query.filter(webhook_deliveries::status.eq(status));
+        }
+
+        query
+            .order_by(webhook_deliveries::created_at.desc())
+            .paginate(q.get_page())
+            .per_page(q.get_per_page())
+            .load_and_count_pages::<WebhookDelivery>(&mut pool.conn())
+            .into_app_result()
+    }
+
+    pub fn create(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        mail_id: Uuid,
+        event: String,
+        url: String,
+        payload: String,
+    ) -> AppResult<WebhookDelivery> {
+        diesel::insert_into(webhook_deliveries::dsl::webhook_deliveries)
+            .values(WebhookDelivery {
+                webhook_delivery_id: Uuid::new_v4(),
+                application_id: app_id,
+                mail_id,
+                event,
+                url,
+                payload,
+                attempts: 0,
+                response_status: None,
+                response_body: None,
+                status: WebhookDeliveryStatus::Pending.to_string(),
+                next_attempt_at: None,
+                delivered_at: None,
+                created_at: current_timestamp(),
+                updated_at: current_timestamp(),
+            })
+            .get_result::<WebhookDelivery>(&mut pool.conn())
+            .into_app_result()
+    }
+
+    pub fn find_by_id(&mut self, pool: &DBPool, id: Uuid) -> AppResult<WebhookDelivery> {
+        webhook_deliveries::table
+            .filter(webhook_deliveries::webhook_delivery_id.eq(id))
+            .first::<WebhookDelivery>(&mut pool.conn())
+            .required("webhook delivery")
+    }
+}
@@ -30,6 +30,16 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    application_smtp_relays (application_smtp_relay_id) {
+        application_smtp_relay_id -> Uuid,
+        application_id -> Uuid,
+        smtp_relay_id -> Uuid,
+        created_by -> Uuid,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     applications (application_id) {
         application_id -> Uuid,
@@ -51,6 +61,11 @@ diesel::table! {
         created_at -> Timestamp,
         updated_at -> Timestamp,
         deleted_at -> Nullable<Timestamp>,
+        max_attachment_size -> Int8,
+        rate_limit_per_second -> Nullable<Int4>,
+        rate_limit_per_minute -> Nullable<Int4>,
+        rate_limit_per_day -> Nullable<Int4>,
+        max_recipients -> Nullable<Int4>,
     }
 }
 
@@ -117,12 +132,50 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    mail_attachments (mail_attachment_id) {
+        mail_attachment_id -> Uuid,
+        mail_id -> Uuid,
+        file_upload_id -> Nullable<Uuid>,
+        #[max_length = 255]
+        file_name -> Varchar,
+        #[max_length = 150]
+        content_type -> Varchar,
+        size -> Int4,
+        content -> Bytea,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     mail_errors (mail_error_id) {
         mail_error_id -> Uuid,
         mail_id -> Uuid,
         smtp_error -> Text,
         created_at -> Timestamp,
+        smtp_code -> Nullable<Int2>,
+        #[max_length = 20]
+        enhanced_code -> Nullable<Varchar>,
+        is_permanent -> Bool,
+    }
+}
+
+diesel::table! {
+    mail_templates (mail_template_id) {
+        mail_template_id -> Uuid,
+        application_id -> Uuid,
+        created_by -> Uuid,
+        #[max_length = 150]
+        name -> Varchar,
+        version -> Int4,
+        #[max_length = 250]
+        subject -> Varchar,
+        html -> Text,
+        text -> Nullable<Text>,
+        is_latest -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
     }
 }
 
@@ -149,6 +202,19 @@ diesel::table! {
         next_retrial_at -> Nullable<Timestamp>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        #[max_length = 250]
+        reference -> Nullable<Varchar>,
+        smtp_response_code -> Nullable<Int2>,
+        smtp_response -> Nullable<Text>,
+        #[max_length = 250]
+        smtp_queue_id -> Nullable<Varchar>,
+        #[max_length = 250]
+        message_id -> Nullable<Varchar>,
+        text_message -> Nullable<Text>,
+        send_at -> Nullable<Timestamp>,
+        #[max_length = 20]
+        priority -> Varchar,
+        suppressed_recipients -> Nullable<Jsonb>,
     }
 }
 
@@ -250,6 +316,46 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    smtp_relays (smtp_relay_id) {
+        smtp_relay_id -> Uuid,
+        created_by -> Uuid,
+        #[max_length = 150]
+        name -> Varchar,
+        #[max_length = 250]
+        host -> Varchar,
+        port -> Int4,
+        #[max_length = 50]
+        encryption -> Varchar,
+        #[max_length = 250]
+        username -> Nullable<Varchar>,
+        password -> Nullable<Text>,
+        priority -> Int2,
+        weight -> Int2,
+        is_active -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    suppressions (suppression_id) {
+        suppression_id -> Uuid,
+        application_id -> Nullable<Uuid>,
+        created_by -> Nullable<Uuid>,
+        #[max_length = 250]
+        email -> Varchar,
+        #[max_length = 50]
+        reason -> Varchar,
+        #[max_length = 50]
+        source -> Varchar,
+        description -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     ui_menu_items (ui_menu_item_id) {
         ui_menu_item_id -> Uuid,
@@ -373,14 +479,43 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    webhook_deliveries (webhook_delivery_id) {
+        webhook_delivery_id -> Uuid,
+        application_id -> Uuid,
+        mail_id -> Uuid,
+        #[max_length = 50]
+        event -> Varchar,
+        #[max_length = 1000]
+        url -> Varchar,
+        payload -> Text,
+        attempts -> Int2,
+        response_status -> Nullable<Int2>,
+        response_body -> Nullable<Text>,
+        #[max_length = 50]
+        status -> Varchar,
+        next_attempt_at -> Nullable<Timestamp>,
+        delivered_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::joinable!(announcements -> users (sender_id));
 diesel::joinable!(app_keys -> applications (application_id));
 diesel::joinable!(app_keys -> users (created_by));
+diesel::joinable!(application_smtp_relays -> applications (application_id));
+diesel::joinable!(application_smtp_relays -> smtp_relays (smtp_relay_id));
+diesel::joinable!(application_smtp_relays -> users (created_by));
 diesel::joinable!(applications -> users (created_by));
 diesel::joinable!(auth_attempts -> users (user_id));
 diesel::joinable!(file_uploads -> users (uploader_id));
 diesel::joinable!(mail_addresses -> mails (mail_id));
+diesel::joinable!(mail_attachments -> file_uploads (file_upload_id));
+diesel::joinable!(mail_attachments -> mails (mail_id));
 diesel::joinable!(mail_errors -> mails (mail_id));
+diesel::joinable!(mail_templates -> applications (application_id));
+diesel::joinable!(mail_templates -> users (created_by));
 diesel::joinable!(mails -> applications (application_id));
 diesel::joinable!(mails -> users (created_by));
 diesel::joinable!(notifications -> users (receiver_id));
@@ -391,6 +526,9 @@ diesel::joinable!(role_permissions -> permissions (permission_id));
 diesel::joinable!(role_permissions -> roles (role_id));
 diesel::joinable!(role_permissions -> users (created_by));
 diesel::joinable!(roles -> users (created_by));
+diesel::joinable!(smtp_relays -> users (created_by));
+diesel::joinable!(suppressions -> applications (application_id));
+diesel::joinable!(suppressions -> users (created_by));
 diesel::joinable!(ui_menu_items -> ui_menus (ui_menu_id));
 diesel::joinable!(ui_menu_items -> users (created_by));
 diesel::joinable!(ui_menus -> users (created_by));
@@ -399,15 +537,20 @@ diesel::joinable!(user_permissions -> permissions (permission_id));
 diesel::joinable!(user_roles -> roles (role_id));
 diesel::joinable!(user_ui_menu_items -> ui_menu_items (ui_menu_item_id));
 diesel::joinable!(user_ui_menu_items -> ui_menus (ui_menu_id));
+diesel::joinable!(webhook_deliveries -> applications (application_id));
+diesel::joinable!(webhook_deliveries -> mails (mail_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     announcements,
     app_keys,
+    application_smtp_relays,
     applications,
     auth_attempts,
     file_uploads,
     mail_addresses,
+    mail_attachments,
     mail_errors,
+    mail_templates,
     mails,
     notifications,
     password_resets,
@@ -415,6 +558,8 @@ diesel::allow_tables_to_appear_in_same_query!(
     personal_access_tokens,
     role_permissions,
     roles,
+    smtp_relays,
+    suppressions,
     ui_menu_items,
     ui_menus,
     user_apps,
@@ -422,4 +567,5 @@ diesel::allow_tables_to_appear_in_same_query!(
     user_roles,
     user_ui_menu_items,
     users,
+    webhook_deliveries,
 );
@@ -4,7 +4,7 @@ use uuid::Uuid;
 
 use crate::app_state::AppState;
 use crate::models::announcement::{Announcement, AnnouncementCreateForm};
-use crate::models::mail::MailBox;
+use crate::models::mail::{MailBox, MailPriority};
 use crate::models::user::FullName;
 use crate::repositories::announcement_repository::AnnouncementRepository;
 use crate::repositories::user_repository::UserRepository;
@@ -33,8 +33,9 @@ impl AnnouncementService {
         ctx.insert("subject", &announcement.title);
         ctx.insert("message", &announcement.message);
         MailerService::new(app)
+            .priority(MailPriority::Bulk)
             .subject(format!("Announcement: {}", form.title.clone()))
-            .view("message", ctx)
+            .view_or("message", ctx, announcement.message.clone())
             .receivers(receivers)
             .for_each_recv()
             .send_silently();
@@ -2,9 +2,13 @@ use crate::helpers::db::DatabaseConnectionHelper;
 use diesel::SaveChangesDsl;
 use uuid::Uuid;
 
+use crate::enums::app_message::AppMessage;
+use crate::helpers::hmac::hmac_verify_signature;
 use crate::helpers::DBPool;
 use crate::models::app_key::AppKey;
+use crate::models::application::ApplicationStatus;
 use crate::repositories::app_key_repository::AppKeyRepository;
+use crate::repositories::application_repository::{app_stringy_status, ApplicationRepository};
 use crate::results::app_result::FormatAppResult;
 use crate::results::http_result::ErroneousOptionResponse;
 use crate::results::AppResult;
@@ -21,6 +25,36 @@ impl AppKeyService {
         AppKeyRepository.generate(pool, app_id, created_by)
     }
 
+    /// Resolves the active key of the given public key and verifies that the body
+    /// has been signed with its private key, see `hmac_verify_signature`
+    pub fn authenticate(
+        &mut self,
+        pool: &DBPool,
+        public_key: String,
+        signature: String,
+        body: &str,
+        tolerance: i64,
+    ) -> AppResult<AppKey> {
+        let key = match AppKeyRepository.find_active_by_public_key(pool, public_key) {
+            Ok(key) => key,
+            Err(AppMessage::EntityNotFound(_)) => {
+                return Err(AppMessage::UnAuthorizedMessage("Invalid application key"))
+            }
+            Err(err) => return Err(err),
+        };
+
+        if !hmac_verify_signature(&signature, body, &key.private_key, tolerance) {
+            return Err(AppMessage::UnAuthorizedMessage("Invalid request signature"));
+        }
+
+        let application = ApplicationRepository.find_by_id(pool, key.application_id)?;
+        if application.status != app_stringy_status(ApplicationStatus::Active) {
+            return Err(AppMessage::UnAuthorizedMessage("Application is not active"));
+        }
+
+        Ok(key)
+    }
+
     pub fn mark_key_as_expired(&mut self, pool: &DBPool, id: Uuid) -> AppResult<AppKey> {
         let mut key = AppKeyRepository.find_by_id(pool, id)?;
         key.status = String::from("expired");
@@ -1,6 +1,8 @@
 use diesel::SaveChangesDsl;
 use uuid::Uuid;
 
+use crate::app_state::AppState;
+use crate::enums::app_message::AppMessage;
 use crate::helpers::db::DatabaseConnectionHelper;
 use crate::helpers::DBPool;
 use crate::models::application::{
@@ -31,6 +33,23 @@ impl ApplicationService {
         ApplicationRepository.update(pool, id, form)
     }
 
+    /// Applications may not accept attachments the request body limit would not let through
+    pub fn verify_max_attachment_size(
+        &mut self,
+        app: &AppState,
+        size: Option<i64>,
+    ) -> AppResult<()> {
+        match size {
+            Some(size) if size < 1 || size as u64 > app.max_attachment_size => {
+                Err(AppMessage::WarningMessage(format!(
+                    "max_attachment_size must be between 1 and {} bytes",
+                    app.max_attachment_size
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub fn activate(&mut self, pool: &DBPool, id: Uuid) -> AppResult<Application> {
         let mut app = ApplicationRepository.find_by_id(pool, id)?;
         app.status = app_stringy_status(ApplicationStatus::Active).to_string();
@@ -13,7 +13,7 @@ use crate::helpers::security::{generate_token, AuthTokenData};
 use crate::helpers::string::password_verify;
 use crate::helpers::DBPool;
 use crate::models::auth_attempt::{AuthAttempt, AuthAttemptStatus, CreateDto};
-use crate::models::mail::MailBox;
+use crate::models::mail::{MailBox, MailPriority};
 use crate::models::user::{
     FullName, LoginForm, User, UserRegisterForm, UserSharableData, UserStatus,
 };
@@ -164,11 +164,18 @@ impl AuthService {
         context.insert("full_name", &user.full_name());
         context.insert("code", &code.to_owned());
 
+        let fallback = format!(
+            "Hi {}, your device verification code is {}",
+            user.full_name(),
+            code
+        );
+
         let subject = app.title("Device Verification");
         MailerService::new(app)
+            .priority(MailPriority::High)
             .subject(subject)
             .receivers(vec![MailBox::new(&user.full_name(), user.email.as_str())])
-            .view("otp", context)
+            .view_or("otp", context, fallback)
             .send_silently();
     }
 
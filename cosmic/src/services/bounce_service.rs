@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+use std::ops::DerefMut;
+
+use diesel::SaveChangesDsl;
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::enums::app_message::AppMessage;
+use crate::helpers::get_db_conn;
+use crate::helpers::time::current_timestamp;
+use crate::models::delivery_status::{
+    DeliveryStatusProcessed, DeliveryStatusRecipient, DeliveryStatusReport,
+};
+use crate::models::mail::{Mail, MailStatus};
+use crate::models::mail_error::SmtpErrorDetail;
+use crate::models::suppression::{SuppressionCreateForm, SuppressionReason, SuppressionSource};
+use crate::models::webhook_delivery::WebhookEvent;
+use crate::repositories::mail_repository::MailRepository;
+use crate::repositories::suppression_repository::SuppressionRepository;
+use crate::results::app_result::FormatAppResult;
+use crate::results::AppResult;
+use crate::services::mail_error_service::MailErrorService;
+use crate::services::mail_service::MailService;
+use crate::services::webhook_service::WebhookService;
+
+/// local part of the return path, followed by `+<mail_id>`
+const VERP_LOCAL_PART: &str = "bounces";
+
+pub struct BounceService;
+
+impl BounceService {
+    /// VERP return path of the mail, e.g. `bounces+<mail_id>@bounces.example.com`
+    pub fn return_path(&mut self, app: &AppState, mail_id: Uuid) -> Option<String> {
+        app.bounce_domain
+            .as_ref()
+            .map(|domain| format!("{}+{}@{}", VERP_LOCAL_PART, mail_id, domain))
+    }
+
+    /// Records the failures a delivery status notification reports on the matching mail,
+    /// permanently failed recipients are suppressed for the mail's application and
+    /// a sent mail whose every recipient permanently failed is marked as bounced
+    pub fn process(&mut self, app: &AppState, raw: &str) -> AppResult<DeliveryStatusProcessed> {
+        let report = self.parse(raw);
+        let mail_id = report.mail_id.ok_or(AppMessage::WarningMessageStr(
+            "No mail could be matched to the report",
+        ))?;
+
+        if report.recipients.is_empty() {
+            return Err(AppMessage::WarningMessageStr(
+                "No delivery status could be found in the report",
+            ));
+        }
+
+        let pool = app.database();
+        let saved = MailService.find_saved(pool, MailRepository.find_by_id(pool, mail_id)?)?;
+
+        // a repeated notification must not record the failures nor notify the application again
+        if saved.mail.status == MailStatus::Bounced.to_string() {
+            return Ok(DeliveryStatusProcessed {
+                mail_id,
+                status: saved.mail.status,
+                failed: vec![],
+                delayed: vec![],
+            });
+        }
+
+        let recipients: Vec<String> = saved
+            .receiver
+            .iter()
+            .chain(saved.cc.iter())
+            .chain(saved.bcc.iter())
+            .map(|mailbox| mailbox.email.to_lowercase())
+            .collect();
+
+        let mut failed = vec![];
+        let mut delayed = vec![];
+        let mut bounced = vec![];
+        for recipient in report.recipients {
+            // a report may list any address, only the mail's own recipients are trusted
+            if !recipients.contains(&recipient.recipient.to_lowercase()) {
+                continue;
+            }
+
+            let permanent = match recipient.action.as_str() {
+                "failed" => recipient
+                    .status
+                    .as_ref()
+                    .is_none_or(|status| status.starts_with('5')),
+                "delayed" => false,
+                _ => continue,
+            };
+
+            let smtp_code = recipient
+                .diagnostic_code
+                .as_ref()
+                .and_then(|code| code.split_whitespace().next())
+                .and_then(|code| code.parse::<i16>().ok());
+
+            let diagnostic = recipient
+                .diagnostic_code
+                .clone()
+                .unwrap_or_else(|| format!("delivery {}", recipient.action));
+
+            MailErrorService.create(
+                pool,
+                mail_id,
+                format!("{}: {}", recipient.recipient, diagnostic),
+                SmtpErrorDetail {
+                    smtp_code,
+                    enhanced_code: recipient.status.clone(),
+                    permanent,
+                },
+            )?;
+
+            if permanent {
+                SuppressionRepository.create(
+                    pool,
+                    None,
+                    SuppressionSource::Dsn,
+                    SuppressionCreateForm {
+                        application_id: Some(saved.mail.application_id),
+                        email: recipient.recipient.clone(),
+                        reason: SuppressionReason::HardBounce.to_string(),
+                        description: Some(diagnostic.clone()),
+                    },
+                )?;
+                bounced.push((recipient.recipient.to_lowercase(), diagnostic));
+            }
+
+            match recipient.action.as_str() {
+                "failed" => failed.push(recipient.recipient),
+                _ => delayed.push(recipient.recipient),
+            }
+        }
+
+        // a mail without recipients (or a report without permanent failures) bounced nothing
+        let every_recipient_bounced = !recipients.is_empty()
+            && !bounced.is_empty()
+            && recipients
+                .iter()
+                .all(|email| bounced.iter().any(|(recipient, _)| recipient == email));
+
+        let mut mail = saved.mail;
+        if every_recipient_bounced && mail.status == MailStatus::Sent.to_string() {
+            mail.status = MailStatus::Bounced.to_string();
+            mail.updated_at = current_timestamp();
+            mail = mail
+                .save_changes::<Mail>(get_db_conn(pool).deref_mut())
+                .into_app_result()?;
+
+            let error = bounced.first().map(|(_, diagnostic)| diagnostic.clone());
+            let _ = WebhookService.dispatch(app, &mail, WebhookEvent::Bounced, error);
+        }
+
+        Ok(DeliveryStatusProcessed {
+            mail_id,
+            status: mail.status,
+            failed,
+            delayed,
+        })
+    }
+
+    /// Reads the per-recipient fields of a `message/delivery-status` report,
+    /// the message is split into blocks of header fields, MIME boundaries are skipped
+    pub fn parse(&mut self, raw: &str) -> DeliveryStatusReport {
+        let raw = raw.replace("\r\n", "\n");
+        let blocks: Vec<HashMap<String, String>> =
+            raw.split("\n\n").map(Self::parse_fields).collect();
+
+        let after_semicolon = |value: &String| -> String {
+            value
+                .split_once(';')
+                .map(|(_, value)| value)
+                .unwrap_or(value)
+                .trim()
+                .trim_matches(|c| c == '<' || c == '>')
+                .to_string()
+        };
+
+        let recipients = blocks
+            .iter()
+            .filter_map(|fields| {
+                let recipient = fields
+                    .get("final-recipient")
+                    .or_else(|| fields.get("original-recipient"))?;
+
+                Some(DeliveryStatusRecipient {
+                    recipient: after_semicolon(recipient),
+                    action: fields.get("action")?.trim().to_lowercase(),
+                    status: fields
+                        .get("status")
+                        .and_then(|status| status.split_whitespace().next())
+                        .map(|status| status.to_string()),
+                    diagnostic_code: fields.get("diagnostic-code").map(after_semicolon),
+                })
+            })
+            .collect();
+
+        // the returned headers carry the Message-ID derived from the mail id
+        let mail_id = Self::find_verp_mail_id(&raw).or_else(|| {
+            blocks.iter().skip(1).find_map(|fields| {
+                let message_id = fields.get("message-id")?.trim().trim_start_matches('<');
+                Uuid::parse_str(message_id.split('@').next()?).ok()
+            })
+        });
+
+        DeliveryStatusReport {
+            mail_id,
+            recipients,
+        }
+    }
+
+    fn find_verp_mail_id(raw: &str) -> Option<Uuid> {
+        let prefix = format!("{}+", VERP_LOCAL_PART);
+        raw.match_indices(&prefix).find_map(|(index, _)| {
+            let start = index + prefix.len();
+            raw.get(start..start + 36)
+                .and_then(|id| Uuid::parse_str(id).ok())
+        })
+    }
+
+    /// Unfolds `Name: value` lines into a map keyed by the lowercased name
+    fn parse_fields(block: &str) -> HashMap<String, String> {
+        let mut fields: HashMap<String, String> = HashMap::new();
+        let mut last: Option<String> = None;
+        for line in block.lines() {
+            if line.starts_with([' ', '\t']) {
+                if let Some(value) = last.as_ref().and_then(|name| fields.get_mut(name)) {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+                continue;
+            }
+
+            last = None;
+            if let Some((name, value)) = line.split_once(':')
+                && !name.is_empty()
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            {
+                let name = name.to_lowercase();
+                if !fields.contains_key(&name) {
+                    fields.insert(name.clone(), value.trim().to_string());
+                    last = Some(name);
+                }
+            }
+        }
+
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIL_ID: &str = "0b7f3c1e-9a5d-4f2b-8c6e-1d2a3b4c5d6e";
+
+    fn report(to: &str, original_headers: &str, fields: &str) -> String {
+        format!(
+            "From: MAILER-DAEMON@mx.example.com\n\
+             To: {to}\n\
+             Message-ID: <20240101.ABC@mx.example.com>\n\
+             Content-Type: multipart/report; report-type=delivery-status; boundary=\"b\"\n\
+             \n\
+             --b\n\
+             Content-Type: text/plain\n\
+             \n\
+             Delivery failed.\n\
+             \n\
+             --b\n\
+             Content-Type: message/delivery-status\n\
+             \n\
+             Reporting-MTA: dns; mx.example.com\n\
+             \n\
+             {fields}\n\
+             \n\
+             --b\n\
+             Content-Type: text/rfc822-headers\n\
+             \n\
+             {original_headers}\n\
+             \n\
+             --b--\n"
+        )
+    }
+
+    #[test]
+    fn reads_failed_and_delayed_recipients() {
+        let raw = report(
+            &format!("bounces+{}@bounces.example.com", MAIL_ID),
+            "Subject: Hello",
+            "Final-Recipient: rfc822; <john@example.com>\n\
+             Action: failed\n\
+             Status: 5.1.1 (bad destination mailbox address)\n\
+             Diagnostic-Code: smtp; 550 5.1.1 User unknown\n\
+             \n\
+             Original-Recipient: rfc822;jane@example.com\n\
+             Action: Delayed\n\
+             Status: 4.2.2",
+        );
+
+        let report = BounceService.parse(&raw);
+        assert_eq!(report.mail_id, Uuid::parse_str(MAIL_ID).ok());
+        assert_eq!(report.recipients.len(), 2);
+
+        let failed = &report.recipients[0];
+        assert_eq!(failed.recipient, "john@example.com");
+        assert_eq!(failed.action, "failed");
+        assert_eq!(failed.status.as_deref(), Some("5.1.1"));
+        assert_eq!(
+            failed.diagnostic_code.as_deref(),
+            Some("550 5.1.1 User unknown")
+        );
+
+        let delayed = &report.recipients[1];
+        assert_eq!(delayed.recipient, "jane@example.com");
+        assert_eq!(delayed.action, "delayed");
+        assert_eq!(delayed.status.as_deref(), Some("4.2.2"));
+        assert_eq!(delayed.diagnostic_code, None);
+    }
+
+    #[test]
+    fn unfolds_fields_and_reads_crlf_reports() {
+        let raw = report(
+            &format!("bounces+{}@bounces.example.com", MAIL_ID),
+            "Subject: Hello",
+            "final-recipient: rfc822; john@example.com\n\
+             ACTION: failed\n\
+             Diagnostic-Code: smtp; 550-5.7.1 Message rejected\n\
+             \t 550 5.7.1 due to policy",
+        )
+        .replace('\n', "\r\n");
+
+        let report = BounceService.parse(&raw);
+        assert_eq!(report.mail_id, Uuid::parse_str(MAIL_ID).ok());
+        assert_eq!(report.recipients.len(), 1);
+        assert_eq!(report.recipients[0].action, "failed");
+        assert_eq!(report.recipients[0].status, None);
+        assert_eq!(
+            report.recipients[0].diagnostic_code.as_deref(),
+            Some("550-5.7.1 Message rejected 550 5.7.1 due to policy")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_returned_message_id() {
+        let raw = report(
+            "noreply@example.com",
+            &format!("Message-ID: <{}@example.com>\nSubject: Hello", MAIL_ID),
+            "Final-Recipient: rfc822; john@example.com\nAction: failed",
+        );
+
+        assert_eq!(
+            BounceService.parse(&raw).mail_id,
+            Uuid::parse_str(MAIL_ID).ok()
+        );
+    }
+
+    #[test]
+    fn skips_invalid_verp_addresses() {
+        let raw = report(
+            "bounces+not-a-uuid@bounces.example.com",
+            &format!("Return-Path: <bounces+{}@bounces.example.com>", MAIL_ID),
+            "Final-Recipient: rfc822; john@example.com\nAction: failed",
+        );
+
+        assert_eq!(
+            BounceService.parse(&raw).mail_id,
+            Uuid::parse_str(MAIL_ID).ok()
+        );
+    }
+
+    #[test]
+    fn skips_recipients_without_an_action() {
+        let raw = report(
+            "noreply@example.com",
+            "Subject: Hello",
+            "Final-Recipient: rfc822; john@example.com\nStatus: 5.1.1",
+        );
+
+        let report = BounceService.parse(&raw);
+        assert_eq!(report.mail_id, None);
+        assert!(report.recipients.is_empty());
+    }
+
+    #[test]
+    fn reads_nothing_from_unrelated_messages() {
+        for raw in [
+            "",
+            "\n\n\n",
+            "Subject: Hello\n\nJust a reply, bounces+ for you",
+        ] {
+            let report = BounceService.parse(raw);
+            assert_eq!(report.mail_id, None);
+            assert!(report.recipients.is_empty());
+        }
+    }
+}
@@ -30,6 +30,26 @@ impl CacheService {
         self.redis.set(key.to_string(), value).into_app_result()
     }
 
+    /// Same as `put`, but the entry expires after `ttl` seconds
+    pub fn put_for<T>(&mut self, key: &str, value: T, ttl: u64) -> AppResult<String>
+    where
+        T: Serialize,
+    {
+        self.redis
+            .set_ex(key.to_string(), value, ttl)
+            .into_app_result()
+    }
+
+    /// Same as `put_for`, but leaves an existing entry untouched, returns whether it was stored
+    pub fn put_if_absent<T>(&mut self, key: &str, value: T, ttl: u64) -> AppResult<bool>
+    where
+        T: Serialize,
+    {
+        self.redis
+            .set_nx_ex(key.to_string(), value, ttl)
+            .into_app_result()
+    }
+
     pub fn get<T: DeserializeOwned>(&mut self, key: &str) -> AppResult<Option<T>> {
         let data = self
             .redis
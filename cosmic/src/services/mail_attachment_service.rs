@@ -0,0 +1,138 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use uuid::Uuid;
+
+use crate::enums::app_message::AppMessage;
+use crate::models::application::Application;
+use crate::models::mail_attachment::{MailAttachment, MailAttachmentData, MailAttachmentPayload};
+use crate::models::DBPool;
+use crate::repositories::file_upload_repository::FileUploadRepository;
+use crate::repositories::mail_attachment_repository::MailAttachmentRepository;
+use crate::results::AppResult;
+
+pub struct MailAttachmentService;
+
+impl MailAttachmentService {
+    /// Turns submitted attachments into queueable ones, uploaded files are read into the payload
+    /// so that the executor does not need access to the upload directory
+    pub fn resolve(
+        &mut self,
+        pool: &DBPool,
+        application: &Application,
+        uploader_id: Uuid,
+        attachments: Vec<MailAttachmentData>,
+    ) -> AppResult<Vec<MailAttachmentPayload>> {
+        let mut total_size = 0;
+        let mut resolved = vec![];
+        for attachment in attachments {
+            let (file_name, content) = match (attachment.file_upload_id, attachment.content) {
+                (Some(id), _) => {
+                    let file = FileUploadRepository.find_by_id(pool, id)?;
+                    if file.uploader_id != uploader_id {
+                        return Err(AppMessage::EntityNotFound(String::from("file upload")));
+                    }
+
+                    // checked before reading, an oversized upload is never loaded into memory
+                    let size = std::fs::metadata(&file.file_path)?.len() as i64;
+                    Self::verify_total_size(application, total_size + size)?;
+
+                    let name = attachment.file_name.unwrap_or(file.orig_name);
+                    (name, std::fs::read(file.file_path)?)
+                }
+                (None, Some(content)) => {
+                    let name = attachment.file_name.ok_or_else(|| {
+                        AppMessage::WarningMessageStr("attachment file_name is required")
+                    })?;
+
+                    let content = STANDARD.decode(content).map_err(|_| {
+                        AppMessage::WarningMessage(format!(
+                            "attachment {} is not valid base64",
+                            name
+                        ))
+                    })?;
+
+                    (name, content)
+                }
+                (None, None) => {
+                    return Err(AppMessage::WarningMessageStr(
+                        "attachment requires either content or file_upload_id",
+                    ));
+                }
+            };
+
+            total_size += content.len() as i64;
+            Self::verify_total_size(application, total_size)?;
+
+            let content_type = attachment
+                .content_type
+                .unwrap_or_else(|| Self::guess_content_type(&file_name).to_string());
+
+            resolved.push(MailAttachmentPayload {
+                file_upload_id: attachment.file_upload_id,
+                file_name,
+                content_type,
+                content: STANDARD.encode(content),
+            });
+        }
+
+        Ok(resolved)
+    }
+
+    pub fn create(
+        &mut self,
+        pool: &DBPool,
+        mail_id: Uuid,
+        attachments: Vec<MailAttachmentPayload>,
+    ) -> AppResult<Vec<MailAttachment>> {
+        let mut created = vec![];
+        for attachment in attachments {
+            let content = STANDARD.decode(attachment.content).map_err(|_| {
+                AppMessage::WarningMessageStr("attachment content is not valid base64")
+            })?;
+
+            created.push(MailAttachmentRepository.create(
+                pool,
+                mail_id,
+                attachment.file_upload_id,
+                attachment.file_name,
+                attachment.content_type,
+                content,
+            )?);
+        }
+
+        Ok(created)
+    }
+
+    fn verify_total_size(application: &Application, total_size: i64) -> AppResult<()> {
+        if total_size > application.max_attachment_size {
+            return Err(AppMessage::WarningMessage(format!(
+                "attachments must not exceed {} bytes",
+                application.max_attachment_size
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn guess_content_type(file_name: &str) -> &'static str {
+        let ext = file_name.rsplit('.').next().unwrap_or_default();
+        match ext.to_lowercase().as_str() {
+            "pdf" => "application/pdf",
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "gif" => "image/gif",
+            "svg" => "image/svg+xml",
+            "txt" => "text/plain",
+            "csv" => "text/csv",
+            "html" | "htm" => "text/html",
+            "json" => "application/json",
+            "xml" => "application/xml",
+            "zip" => "application/zip",
+            "doc" => "application/msword",
+            "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            "xls" => "application/vnd.ms-excel",
+            "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            _ => "application/octet-stream",
+        }
+    }
+}
@@ -1,8 +1,10 @@
-use crate::models::mail_error::MailError;
+use lettre::transport::smtp::Error as SmtpError;
+use uuid::Uuid;
+
+use crate::models::mail_error::{MailError, SmtpErrorDetail};
 use crate::models::DBPool;
 use crate::repositories::mail_error_repository::MailErrorRepository;
 use crate::results::AppResult;
-use uuid::Uuid;
 
 pub struct MailErrorService;
 
@@ -12,7 +14,132 @@ impl MailErrorService {
         pool: &DBPool,
         mail_id: Uuid,
         smtp_error: String,
+        detail: SmtpErrorDetail,
     ) -> AppResult<MailError> {
-        MailErrorRepository.create(pool, mail_id, smtp_error)
+        MailErrorRepository.create(pool, mail_id, smtp_error, detail)
+    }
+
+    /// Decides whether a failed delivery is worth retrying:
+    /// - an enhanced status code (RFC 3463) takes precedence, `5.x.x` is permanent, `4.x.x` is transient
+    /// - otherwise a `5xx` reply is permanent and a `4xx` reply is transient
+    /// - connection, tls and timeout errors carry no reply and are always transient
+    /// - errors raised while building the message are permanent, retrying them cannot help
+    pub fn classify(&mut self, error: &SmtpError) -> SmtpErrorDetail {
+        let smtp_code = error
+            .status()
+            .and_then(|code| code.to_string().parse().ok());
+
+        Self::classify_reply(
+            smtp_code,
+            &error.to_string(),
+            error.is_permanent() || error.is_client(),
+        )
+    }
+
+    /// `rejected` tells whether the error itself is a permanent one, used when no enhanced code is found
+    fn classify_reply(smtp_code: Option<i16>, message: &str, rejected: bool) -> SmtpErrorDetail {
+        let enhanced_code = Self::parse_enhanced_code(message);
+
+        let permanent = match &enhanced_code {
+            Some(code) => code.starts_with('5'),
+            None => rejected,
+        };
+
+        SmtpErrorDetail {
+            smtp_code,
+            enhanced_code,
+            permanent,
+        }
+    }
+
+    fn parse_enhanced_code(message: &str) -> Option<String> {
+        message
+            .split(|c: char| {
+                c.is_whitespace() || matches!(c, ',' | ':' | ';' | '-' | '(' | ')' | '[' | ']')
+            })
+            .find(|token| {
+                let parts: Vec<&str> = token.split('.').collect();
+                parts.len() == 3
+                    && matches!(parts[0], "2" | "4" | "5")
+                    && parts[1..].iter().all(|part| {
+                        (1..=3).contains(&part.len()) && part.chars().all(|c| c.is_ascii_digit())
+                    })
+            })
+            .map(|token| token.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_enhanced_code_in_a_reply() {
+        assert_eq!(
+            MailErrorService::parse_enhanced_code("permanent error (550): 5.1.1 User unknown"),
+            Some("5.1.1".to_string())
+        );
+        assert_eq!(
+            MailErrorService::parse_enhanced_code(
+                "451 4.7.650 The mail server has been temporarily rate limited"
+            ),
+            Some("4.7.650".to_string())
+        );
+        assert_eq!(
+            MailErrorService::parse_enhanced_code(
+                "550-5.7.26 Unauthenticated email,5.7.26 rejected"
+            ),
+            Some("5.7.26".to_string())
+        );
+        assert_eq!(
+            MailErrorService::parse_enhanced_code("mailbox full (4.2.2); try later"),
+            Some("4.2.2".to_string())
+        );
+    }
+
+    #[test]
+    fn ignores_tokens_that_only_look_like_codes() {
+        for message in [
+            "",
+            "connection refused",
+            "permanent error (550): no such user",
+            "connect to 10.0.0.1 failed",
+            "3.1.1 is not a class",
+            "5.1 too short",
+            "5.1.1234 subject too long",
+            "5.a.1 not numeric",
+            "5..1 empty subject",
+        ] {
+            assert_eq!(
+                MailErrorService::parse_enhanced_code(message),
+                None,
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn enhanced_code_takes_precedence_over_the_reply() {
+        let detail = MailErrorService::classify_reply(Some(550), "550 4.2.2 Mailbox full", true);
+        assert_eq!(detail.smtp_code, Some(550));
+        assert_eq!(detail.enhanced_code.as_deref(), Some("4.2.2"));
+        assert!(!detail.permanent);
+
+        let detail = MailErrorService::classify_reply(Some(451), "451 5.7.1 Rejected", false);
+        assert!(detail.permanent);
+    }
+
+    #[test]
+    fn falls_back_to_the_reply_without_an_enhanced_code() {
+        assert!(MailErrorService::classify_reply(Some(550), "550 No such user", true).permanent);
+        assert!(
+            !MailErrorService::classify_reply(Some(421), "421 Try again later", false).permanent
+        );
+
+        let detail = MailErrorService::classify_reply(None, "connection timed out", false);
+        assert_eq!(detail.smtp_code, None);
+        assert_eq!(detail.enhanced_code, None);
+        assert!(!detail.permanent);
     }
 }
@@ -1,30 +1,430 @@
 use std::ops::DerefMut;
+use std::str::FromStr;
 
+use actix_web::http::StatusCode;
+use chrono::{Duration, NaiveDateTime};
 use diesel::SaveChangesDsl;
+use lettre::address::{Address, Envelope};
 use lettre::message::header::ContentType;
-use lettre::message::Mailbox;
-use lettre::{Message, Transport};
-use log::error;
+use lettre::message::{Attachment, Mailbox, MultiPart};
+use lettre::{AsyncTransport, Message};
+use log::{error, info, warn};
+use rand::Rng;
 use redis::Commands;
 use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
 
 use crate::app_state::AppState;
+use crate::enums::app_message::AppMessage;
 use crate::helpers::get_db_conn;
-use crate::models::mail::{Mail, MailBox, MailFailureResponse, MailStatus, MailSuccessResponse};
+use crate::helpers::html::html_to_text;
+use crate::helpers::http::QueryParams;
+use crate::helpers::time::current_timestamp;
+use crate::models::mail::{
+    DelayedQueueItem, IdempotencyRecord, MailCallbackPayload, MailData, MailDetail,
+    MailFilterParams, MailQueued,
+};
+use crate::models::mail::{
+    Mail, MailBox, MailFailureResponse, MailPriority, MailStatus, MailSuccessResponse,
+};
 use crate::models::mail::{MailQueueablePayload, MailSaved};
 use crate::models::mail_address::{MailAddress, MailAddressType};
+use crate::models::mail_error::SmtpErrorDetail;
+use crate::models::mail_template::MailTemplateRef;
+use crate::models::suppression::SuppressedRecipient;
+use crate::models::webhook_delivery::WebhookEvent;
 use crate::models::DBPool;
+use crate::repositories::application_repository::ApplicationRepository;
+use crate::repositories::mail_address_repository::MailAddressRepository;
+use crate::repositories::mail_attachment_repository::MailAttachmentRepository;
+use crate::repositories::mail_error_repository::MailErrorRepository;
 use crate::repositories::mail_repository::MailRepository;
 use crate::results::app_result::FormatAppResult;
-use crate::results::{AppResult, RedisResult};
+use crate::results::{AppPaginationResult, AppResult, RedisResult};
+use crate::services::bounce_service::BounceService;
 use crate::services::mail_address_service::MailAddressService;
+use crate::services::mail_attachment_service::MailAttachmentService;
 use crate::services::mail_error_service::MailErrorService;
+use crate::services::mail_template_service::MailTemplateService;
+use crate::services::queue_service::QueueService;
+use crate::services::rate_limit_service::RateLimitService;
+use crate::services::smtp_relay_service::SmtpRelayService;
+use crate::services::suppression_service::SuppressionService;
+use crate::services::webhook_service::WebhookService;
+
+/// A validated mail of a submission, queued once the application's rate limit is reserved
+struct PendingMail {
+    /// index of the mail in the submission
+    position: usize,
+    key: Option<String>,
+    hash: String,
+    payload: MailQueueablePayload,
+}
+
+/// Pops due members of the delayed set & pushes their payloads onto their queues,
+/// members that cannot be decoded are dropped
+const RELEASE_DUE_ITEMS_SCRIPT: &str = r#"
+local members = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1], 'LIMIT', 0, ARGV[2])
+local released, invalid = 0, 0
+for _, member in ipairs(members) do
+    redis.call('ZREM', KEYS[1], member)
+    local ok, item = pcall(cjson.decode, member)
+    if ok and type(item) == 'table' and type(item.queue) == 'string'
+        and type(item.payload) == 'string' then
+        redis.call('LPUSH', item.queue, item.payload)
+        released = released + 1
+    else
+        invalid = invalid + 1
+    end
+end
+return {released, invalid}
+"#;
 
 pub struct MailService;
 
 impl MailService {
-    pub fn create(&mut self, pool: &DBPool, payload: MailQueueablePayload) -> AppResult<MailSaved> {
-        let mail = MailRepository.create(pool, payload.clone())?;
+    pub fn list(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        q: QueryParams,
+        filter: MailFilterParams,
+    ) -> AppPaginationResult<Mail> {
+        if let Some(status) = &q.status
+            && MailStatus::from_str(status).is_err()
+        {
+            let msg = format!("Invalid mail status({})", status);
+            return Err(AppMessage::WarningMessage(msg));
+        }
+
+        MailRepository.list_by_application(pool, app_id, q, filter)
+    }
+
+    pub fn find_detailed(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        id: Uuid,
+    ) -> AppResult<MailDetail> {
+        let mail = MailRepository.find_by_application(pool, app_id, id)?;
+        let mail_addresses = MailAddressRepository.list_by_mail_id(pool, mail.mail_id)?;
+        let mail_errors = MailErrorRepository.list_by_mail_id(pool, mail.mail_id)?;
+        let mail_attachments = MailAttachmentRepository.list_by_mail_id(pool, mail.mail_id)?;
+
+        Ok(MailDetail {
+            mail,
+            mail_addresses,
+            mail_errors,
+            mail_attachments,
+        })
+    }
+
+    /// Assigns an id to each mail and pushes it to the awaiting queue,
+    /// a failing item does not prevent the rest from being queued.
+    /// Requests & mails carrying an idempotency key already seen are not queued again,
+    /// the original result is returned instead
+    pub fn enqueue(
+        &mut self,
+        app: &AppState,
+        app_id: Uuid,
+        created_by: Uuid,
+        mails: Vec<MailData>,
+        idempotency_key: Option<String>,
+    ) -> AppResult<Vec<MailQueued>> {
+        let mut cache = app.services.cache.clone();
+        let request_key = idempotency_key.map(|key| self.idempotency_key(app_id, "request", &key));
+        let Some(key) = request_key else {
+            return self.queue_mails(app, app_id, created_by, mails);
+        };
+
+        // the key is claimed before queueing, so concurrent retries cannot both get through
+        let request_hash = self.request_hash(&mails);
+        let pending = IdempotencyRecord::<Vec<MailQueued>> {
+            request_hash: request_hash.clone(),
+            result: None,
+        };
+        if !cache.put_if_absent(&key, pending, app.idempotency_ttl)? {
+            return self.replay(cache.get(&key)?, &request_hash);
+        }
+
+        let queued = match self.queue_mails(app, app_id, created_by, mails) {
+            Ok(queued) => queued,
+            Err(err) => {
+                if let Err(err) = cache.delete(&key) {
+                    error!("[enqueue] failed to release idempotency key: {:?}", err);
+                }
+                return Err(err);
+            }
+        };
+
+        let record = IdempotencyRecord {
+            request_hash,
+            result: Some(queued.clone()),
+        };
+        if let Err(err) = cache.put_for(&key, record, app.idempotency_ttl) {
+            error!("[enqueue] failed to store idempotency key: {:?}", err);
+        }
+
+        Ok(queued)
+    }
+
+    fn queue_mails(
+        &mut self,
+        app: &AppState,
+        app_id: Uuid,
+        created_by: Uuid,
+        mails: Vec<MailData>,
+    ) -> AppResult<Vec<MailQueued>> {
+        let mut cache = app.services.cache.clone();
+        let application = ApplicationRepository.find_by_id(app.database(), app_id)?;
+
+        let mut queued = vec![];
+        let mut pending: Vec<PendingMail> = vec![];
+        for mail in mails {
+            let reference = mail.reference.clone();
+            let rejected = |error: String| MailQueued {
+                mail_id: None,
+                reference: reference.clone(),
+                queued: false,
+                error: Some(error),
+            };
+
+            if mail.receiver.is_empty() {
+                queued.push(rejected(String::from("at least one receiver is required")));
+                continue;
+            }
+
+            let recipients = mail.receiver.len() + mail.cc.len() + mail.bcc.len();
+            if let Some(max) = application.max_recipients
+                && recipients > max as usize
+            {
+                queued.push(rejected(format!(
+                    "mail must not address more than {} recipients",
+                    max
+                )));
+                continue;
+            }
+
+            if reference.as_ref().is_some_and(|r| r.len() > 250) {
+                queued.push(rejected(String::from(
+                    "reference must not exceed 250 characters",
+                )));
+                continue;
+            }
+
+            let mail_key = match &mail.idempotency_key {
+                Some(key) if key.len() > 250 => {
+                    queued.push(rejected(String::from(
+                        "idempotency_key must not exceed 250 characters",
+                    )));
+                    continue;
+                }
+                Some(key) => Some(self.idempotency_key(app_id, "mail", key)),
+                None => None,
+            };
+
+            let mail_hash = self.request_hash(&mail);
+            if let Some(key) = &mail_key
+                && let Some(record) = cache.get::<IdempotencyRecord<MailQueued>>(key)?
+            {
+                match self.replay(Some(record), &mail_hash) {
+                    Ok(original) => queued.push(original),
+                    Err(err) => queued.push(rejected(err.to_string())),
+                }
+                continue;
+            }
+
+            // the template version is pinned at submission, later edits do not affect queued mails
+            let template = match mail.template {
+                Some(name) => {
+                    match MailTemplateService.find(
+                        app.database(),
+                        app_id,
+                        name.clone(),
+                        mail.template_version,
+                    ) {
+                        Ok(found) => Some(MailTemplateRef {
+                            name,
+                            version: Some(found.version),
+                            variables: mail.variables,
+                        }),
+                        Err(err) => {
+                            queued.push(rejected(err.to_string()));
+                            continue;
+                        }
+                    }
+                }
+                None if mail.message.is_empty() => {
+                    queued.push(rejected(String::from(
+                        "either message or template is required",
+                    )));
+                    continue;
+                }
+                None => None,
+            };
+
+            let attachments = match MailAttachmentService.resolve(
+                app.database(),
+                &application,
+                created_by,
+                mail.attachments,
+            ) {
+                Ok(attachments) => attachments,
+                Err(err) => {
+                    queued.push(rejected(err.to_string()));
+                    continue;
+                }
+            };
+
+            if let Some(key) = &mail_key {
+                let pending = IdempotencyRecord::<MailQueued> {
+                    request_hash: mail_hash.clone(),
+                    result: None,
+                };
+                if !cache.put_if_absent(key, pending, app.idempotency_ttl)? {
+                    match self.replay(cache.get(key)?, &mail_hash) {
+                        Ok(original) => queued.push(original),
+                        Err(err) => queued.push(rejected(err.to_string())),
+                    }
+                    continue;
+                }
+            }
+
+            // taken by the loop below, unless the rate limit rejects the request as a whole
+            queued.push(rejected(String::from("failed to queue mail")));
+            pending.push(PendingMail {
+                position: queued.len() - 1,
+                key: mail_key,
+                hash: mail_hash,
+                payload: MailQueueablePayload {
+                    mail_id: Uuid::new_v4(),
+                    reference: mail.reference,
+                    application_id: app_id,
+                    created_by,
+                    subject: mail.subject,
+                    message: mail.message,
+                    text: mail.text,
+                    from: mail.from,
+                    cc: mail.cc,
+                    bcc: mail.bcc,
+                    reply_to: mail.reply_to,
+                    receiver: mail.receiver,
+                    attachments,
+                    template,
+                    send_at: mail.send_at,
+                    priority: mail.priority,
+                },
+            });
+        }
+
+        // only mails that are actually queued count against the rate limit
+        if !pending.is_empty()
+            && let Err(err) = RateLimitService.reserve(app, &application, pending.len() as i64)
+        {
+            for mail in &pending {
+                if let Some(key) = &mail.key
+                    && let Err(err) = cache.delete(key)
+                {
+                    error!("[enqueue] failed to release idempotency key: {:?}", err);
+                }
+            }
+            return Err(err);
+        }
+
+        for mail in pending {
+            let mail_id = mail.payload.mail_id;
+            let reference = mail.payload.reference.clone();
+            match self.push_to_awaiting_queue(app, mail.payload) {
+                Ok(_) => {
+                    let item = MailQueued {
+                        mail_id: Some(mail_id),
+                        reference,
+                        queued: true,
+                        error: None,
+                    };
+
+                    if let Some(key) = &mail.key {
+                        let record = IdempotencyRecord {
+                            request_hash: mail.hash,
+                            result: Some(item.clone()),
+                        };
+                        if let Err(err) = cache.put_for(key, record, app.idempotency_ttl) {
+                            error!("[enqueue] failed to store idempotency key: {:?}", err);
+                        }
+                    }
+
+                    queued[mail.position] = item;
+                }
+                Err(err) => {
+                    error!("[enqueue] failed to queue mail #{}: {:?}", mail_id, err);
+                    if let Some(key) = &mail.key
+                        && let Err(err) = cache.delete(key)
+                    {
+                        error!("[enqueue] failed to release idempotency key: {:?}", err);
+                    }
+                }
+            }
+        }
+
+        Ok(queued)
+    }
+
+    fn idempotency_key(&mut self, app_id: Uuid, scope: &str, key: &str) -> String {
+        format!("idempotency:{}:{}:{}", app_id, scope, key)
+    }
+
+    /// Fingerprint of the submitted data, a key may only be replayed with the same data
+    fn request_hash<T: Serialize>(&mut self, data: &T) -> String {
+        let json = serde_json::to_string(data).unwrap();
+        hex::encode(Sha256::digest(json.as_bytes()))
+    }
+
+    /// Result stored against an idempotency key that was already claimed
+    fn replay<T>(
+        &mut self,
+        record: Option<IdempotencyRecord<T>>,
+        request_hash: &str,
+    ) -> AppResult<T> {
+        match record {
+            Some(record) if record.request_hash != request_hash => Err(AppMessage::ErrorMessage(
+                String::from("idempotency key was already used with a different request"),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            )),
+            Some(IdempotencyRecord {
+                result: Some(result),
+                ..
+            }) => Ok(result),
+            _ => Err(AppMessage::ErrorMessage(
+                String::from("a request with this idempotency key is still being processed"),
+                StatusCode::CONFLICT,
+            )),
+        }
+    }
+
+    pub fn create(
+        &mut self,
+        pool: &DBPool,
+        mut payload: MailQueueablePayload,
+    ) -> AppResult<MailSaved> {
+        // a redelivered payload finds the mail persisted by the previous attempt
+        if let Some(mail) = MailRepository.find_optional(pool, payload.mail_id)? {
+            return self.find_saved(pool, mail);
+        }
+
+        let suppressed = SuppressionService.drop_suppressed(
+            pool,
+            payload.application_id,
+            [&mut payload.receiver, &mut payload.cc, &mut payload.bcc],
+        )?;
+
+        let mut mail = MailRepository.create(pool, payload.clone())?;
+        if !suppressed.is_empty() {
+            let exhausted =
+                payload.receiver.is_empty() && payload.cc.is_empty() && payload.bcc.is_empty();
+            mail = self.record_suppressed(pool, mail, suppressed, exhausted)?;
+        }
+
         let to_mailbox = |addr: MailAddress| MailBox::new(&addr.name, &addr.email);
 
         let mut cc = vec![];
@@ -67,6 +467,8 @@ impl MailService {
             )?));
         }
 
+        MailAttachmentService.create(pool, mail.mail_id, payload.attachments)?;
+
         Ok(MailSaved {
             mail,
             cc,
@@ -76,6 +478,225 @@ impl MailService {
         })
     }
 
+    /// Drops recipients suppressed since the mail was created, returns None when none is left
+    pub fn drop_suppressed_recipients(
+        &mut self,
+        app: &AppState,
+        mut saved: MailSaved,
+    ) -> AppResult<Option<MailSaved>> {
+        let suppressed = SuppressionService.drop_suppressed(
+            app.database(),
+            saved.mail.application_id,
+            [&mut saved.receiver, &mut saved.cc, &mut saved.bcc],
+        )?;
+
+        if suppressed.is_empty() {
+            return Ok(Some(saved));
+        }
+
+        let exhausted = saved.receiver.is_empty() && saved.cc.is_empty() && saved.bcc.is_empty();
+        saved.mail = self.record_suppressed(app.database(), saved.mail, suppressed, exhausted)?;
+
+        if exhausted {
+            let _ = WebhookService.dispatch(app, &saved.mail, WebhookEvent::Suppressed, None);
+            return Ok(None);
+        }
+
+        Ok(Some(saved))
+    }
+
+    /// Records dropped recipients on the mail, a mail left without recipients is marked as suppressed
+    fn record_suppressed(
+        &mut self,
+        pool: &DBPool,
+        mut mail: Mail,
+        suppressed: Vec<SuppressedRecipient>,
+        exhausted: bool,
+    ) -> AppResult<Mail> {
+        let mut recorded: Vec<SuppressedRecipient> = mail
+            .suppressed_recipients
+            .clone()
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        recorded.extend(suppressed);
+
+        mail.suppressed_recipients = Some(serde_json::to_value(recorded)?);
+        if exhausted {
+            mail.status = MailStatus::Suppressed.to_string();
+        }
+
+        mail.updated_at = current_timestamp();
+        mail.save_changes::<Mail>(get_db_conn(pool).deref_mut())
+            .into_app_result()
+    }
+
+    /// Rebuilds the queueable form of an already persisted mail
+    pub fn find_saved(&mut self, pool: &DBPool, mail: Mail) -> AppResult<MailSaved> {
+        let addresses = MailAddressRepository::get_sorted(pool, mail.mail_id)?;
+        let to_mailboxes = |addresses: Vec<MailAddress>| -> Vec<MailBox> {
+            addresses
+                .iter()
+                .map(|addr| MailBox::new(&addr.name, &addr.email))
+                .collect()
+        };
+
+        Ok(MailSaved {
+            mail,
+            cc: to_mailboxes(addresses.cc),
+            bcc: to_mailboxes(addresses.bcc),
+            reply_to: to_mailboxes(addresses.reply_to),
+            receiver: to_mailboxes(addresses.receivers),
+        })
+    }
+
+    /// Moves a scheduled mail to a new date, the previously queued release becomes stale
+    pub fn reschedule(
+        &mut self,
+        app: &AppState,
+        app_id: Uuid,
+        id: Uuid,
+        send_at: NaiveDateTime,
+    ) -> AppResult<Mail> {
+        let pool = app.database();
+        let mut mail = MailRepository.find_by_application(pool, app_id, id)?;
+        if mail.status != MailStatus::Scheduled.to_string() {
+            return Err(AppMessage::WarningMessageStr(
+                "only scheduled mails can be rescheduled",
+            ));
+        }
+
+        if send_at <= current_timestamp() {
+            return Err(AppMessage::WarningMessageStr(
+                "send_at must be in the future",
+            ));
+        }
+
+        mail.send_at = Some(send_at);
+        mail.updated_at = current_timestamp();
+        let mail = mail
+            .save_changes::<Mail>(get_db_conn(pool).deref_mut())
+            .into_app_result()?;
+
+        let saved = self.find_saved(pool, mail.clone())?;
+        self.push_to_scheduled_queue(app, saved)?;
+        Ok(mail)
+    }
+
+    pub fn cancel_scheduled(&mut self, app: &AppState, app_id: Uuid, id: Uuid) -> AppResult<Mail> {
+        let mail = MailRepository.find_by_application(app.database(), app_id, id)?;
+        if mail.status != MailStatus::Scheduled.to_string() {
+            return Err(AppMessage::WarningMessageStr(
+                "only scheduled mails can be unscheduled",
+            ));
+        }
+
+        self.mark_as_cancelled(app, mail)
+    }
+
+    /// Stops a mail that has not been sent yet, the workers skip it once popped
+    pub fn cancel(&mut self, app: &AppState, app_id: Uuid, id: Uuid) -> AppResult<Mail> {
+        let mail = MailRepository.find_by_application(app.database(), app_id, id)?;
+        let cancellable = [
+            MailStatus::Awaiting.to_string(),
+            MailStatus::Retrying.to_string(),
+            MailStatus::Scheduled.to_string(),
+        ];
+
+        if !cancellable.contains(&mail.status) {
+            let msg = format!("mail cannot be cancelled while {}", mail.status);
+            return Err(AppMessage::WarningMessage(msg));
+        }
+
+        self.mark_as_cancelled(app, mail)
+    }
+
+    fn mark_as_cancelled(&mut self, app: &AppState, mut mail: Mail) -> AppResult<Mail> {
+        mail.status = MailStatus::Cancelled.to_string();
+        mail.next_retrial_at = None;
+        mail.updated_at = current_timestamp();
+        let mail = mail
+            .save_changes::<Mail>(get_db_conn(app.database()).deref_mut())
+            .into_app_result()?;
+
+        let _ = WebhookService.dispatch(app, &mail, WebhookEvent::Cancelled, None);
+        Ok(mail)
+    }
+
+    /// Reloads the mail right before it is sent, returns None when it has been cancelled,
+    /// already handled or, for scheduled mails, rescheduled since it was queued
+    pub fn claim_for_sending(
+        &mut self,
+        pool: &DBPool,
+        mut saved: MailSaved,
+    ) -> AppResult<Option<MailSaved>> {
+        let mut mail = MailRepository.find_by_id(pool, saved.mail.mail_id)?;
+        let scheduled = MailStatus::Scheduled.to_string();
+        if saved.mail.status == scheduled {
+            if mail.status != scheduled || mail.send_at != saved.mail.send_at {
+                return Ok(None);
+            }
+
+            mail.status = MailStatus::Awaiting.to_string();
+            mail.updated_at = current_timestamp();
+            mail = mail
+                .save_changes::<Mail>(get_db_conn(pool).deref_mut())
+                .into_app_result()?;
+        }
+
+        let sendable = [
+            MailStatus::Awaiting.to_string(),
+            MailStatus::Retrying.to_string(),
+        ];
+
+        if !sendable.contains(&mail.status) {
+            return Ok(None);
+        }
+
+        saved.mail = mail;
+        Ok(Some(saved))
+    }
+
+    /// Renders the payload's template (if any) into its subject, message & text
+    pub fn apply_template(
+        &mut self,
+        app: &AppState,
+        payload: &mut MailQueueablePayload,
+    ) -> AppResult<()> {
+        let template = match payload.template.clone() {
+            Some(template) => template,
+            None => return Ok(()),
+        };
+
+        if payload.subject.is_empty() {
+            payload.subject = template.name.clone();
+        }
+
+        let rendered = MailTemplateService.render(app, payload.application_id, template)?;
+        payload.subject = rendered.subject;
+        payload.message = rendered.html;
+        payload.text = rendered.text.or(payload.text.take());
+        Ok(())
+    }
+
+    /// Render errors cannot be fixed by retrying, the mail is failed right away
+    pub fn mark_as_render_failure(
+        &mut self,
+        pool: &DBPool,
+        saved: MailSaved,
+        error: String,
+    ) -> AppResult<Mail> {
+        let response = MailFailureResponse {
+            saved_mail: saved,
+            error_message: error,
+            smtp: SmtpErrorDetail {
+                permanent: true,
+                ..SmtpErrorDetail::default()
+            },
+        };
+
+        self.mark_as_failure(pool, response)
+    }
+
     pub fn mark_as_success(
         &mut self,
         pool: &DBPool,
@@ -83,6 +704,12 @@ impl MailService {
     ) -> AppResult<Mail> {
         let mut mail = MailRepository.find_by_id(pool, response.saved_mail.mail.mail_id)?;
         mail.status = MailStatus::Sent.to_string();
+        mail.sent_at = Some(current_timestamp());
+        mail.next_retrial_at = None;
+        mail.smtp_response_code = response.response_code;
+        mail.smtp_response = Some(response.response_body);
+        mail.smtp_queue_id = response.queue_id;
+        mail.message_id = response.message_id;
         mail.save_changes::<Mail>(get_db_conn(pool).deref_mut())
             .into_app_result()
     }
@@ -92,24 +719,27 @@ impl MailService {
         pool: &DBPool,
         response: MailFailureResponse,
         status: MailStatus,
-        increment_trials: bool,
+        next_retrial_at: Option<NaiveDateTime>,
     ) -> AppResult<Mail> {
         let mut mail = MailRepository.find_by_id(pool, response.saved_mail.mail.mail_id)?;
 
-        if increment_trials {
-            mail.trials += 1;
+        // a failure handled again (after its queue item was re-queued) was recorded already
+        if mail.trials > response.saved_mail.mail.trials {
+            return Ok(mail);
         }
 
-        // update mail status
-        if mail.status != status.to_string() {
+        // update mail status, a mail cancelled while it was being sent stays cancelled
+        mail.trials += 1;
+        if mail.status != MailStatus::Cancelled.to_string() {
             mail.status = status.to_string();
-
-            mail.save_changes::<Mail>(get_db_conn(pool).deref_mut())
-                .into_app_result()?;
+            mail.next_retrial_at = next_retrial_at;
         }
+        let mail = mail
+            .save_changes::<Mail>(get_db_conn(pool).deref_mut())
+            .into_app_result()?;
 
         // record mail error
-        MailErrorService.create(pool, mail.mail_id, response.error_message)?;
+        MailErrorService.create(pool, mail.mail_id, response.error_message, response.smtp)?;
 
         Ok(mail)
     }
@@ -119,20 +749,89 @@ impl MailService {
         pool: &DBPool,
         response: MailFailureResponse,
     ) -> AppResult<Mail> {
-        self.log_failure(pool, response, MailStatus::Failed, true)
+        let suppressed = SuppressionService.suppress_rejected(
+            pool,
+            &response.saved_mail,
+            &response.smtp,
+            &response.error_message,
+        );
+
+        if let Err(err) = suppressed {
+            error!(
+                "failed to suppress recipient of mail #{}: {:?}",
+                response.saved_mail.mail.mail_id, err
+            );
+        }
+
+        self.log_failure(pool, response, MailStatus::Failed, None)
     }
 
     pub fn mark_as_retrying(
         &mut self,
         pool: &DBPool,
         response: MailFailureResponse,
+        next_retrial_at: NaiveDateTime,
     ) -> AppResult<Mail> {
-        self.log_failure(pool, response, MailStatus::Retrying, true)
+        self.log_failure(pool, response, MailStatus::Retrying, Some(next_retrial_at))
+    }
+
+    /// Exponential backoff for the given (1-based) trial, capped at `retry_backoff_max`
+    /// and spread by up to `retry_jitter` percent to avoid retrying in bursts
+    pub fn retry_delay(&mut self, app: &AppState, trials: i16) -> Duration {
+        let (delay, jitter) = Self::retry_delay_bounds(
+            app.retry_backoff,
+            app.retry_backoff_max,
+            app.retry_jitter,
+            trials,
+        );
+        let jitter = match jitter > 0 {
+            true => rand::rng().random_range(0..=jitter),
+            false => 0,
+        };
+
+        Duration::seconds(delay + jitter)
+    }
+
+    /// The delay before the given trial along with the most jitter that may be added to it
+    fn retry_delay_bounds(backoff: i64, backoff_max: i64, jitter: i64, trials: i16) -> (i64, i64) {
+        let exponent = (trials.max(1) - 1).min(16) as u32;
+        let delay = backoff
+            .saturating_mul(2_i64.pow(exponent))
+            .min(backoff_max)
+            .max(0);
+        let jitter = delay.saturating_mul(jitter.max(0)) / 100;
+
+        (delay, jitter.min(i64::MAX - delay))
     }
 
     pub async fn send(&mut self, app: &AppState, thread_name: String, saved: MailSaved) {
         let subject = saved.mail.subject.clone();
-        let smtp = app.smtp.clone();
+
+        let saved = match self.drop_suppressed_recipients(app, saved.clone()) {
+            Ok(Some(saved)) => saved,
+            Ok(None) => {
+                info!(
+                    "[{}] every recipient of mail #{} is suppressed, skipping...",
+                    thread_name, subject
+                );
+                return;
+            }
+            Err(err) => {
+                error!(
+                    "[{}] Failed to check suppressions of mail #{}, [error: {}], re-queueing...",
+                    thread_name, subject, err
+                );
+                let _ = self.push_to_failure_notification_queue(
+                    app,
+                    MailFailureResponse {
+                        saved_mail: saved,
+                        error_message: err.to_string(),
+                        smtp: SmtpErrorDetail::default(),
+                    },
+                );
+                return;
+            }
+        };
 
         let make_mailbox = |rec: &MailBox| -> Mailbox {
             Mailbox::new(Some(rec.name.clone()), rec.email.parse().unwrap())
@@ -158,18 +857,113 @@ impl MailService {
             builder = builder.reply_to(make_mailbox(reply_to))
         }
 
-        let email = builder
-            .subject(saved.mail.subject.clone())
-            .header(ContentType::TEXT_HTML)
-            .body(saved.mail.message.clone())
-            .unwrap();
+        let attachments =
+            match MailAttachmentRepository.list_by_mail_id(app.database(), saved.mail.mail_id) {
+                Ok(attachments) => attachments,
+                Err(err) => {
+                    error!(
+                        "[{}] Failed to load attachments of mail #{}, [error: {}], re-queueing...",
+                        thread_name, subject, err
+                    );
+                    let _ = self.push_to_failure_notification_queue(
+                        app,
+                        MailFailureResponse {
+                            saved_mail: saved.clone(),
+                            error_message: err.to_string(),
+                            smtp: SmtpErrorDetail::default(),
+                        },
+                    );
+                    return;
+                }
+            };
+
+        let message_id = self.make_message_id(&saved.mail);
+        let builder = builder
+            .message_id(Some(message_id.clone()))
+            .subject(saved.mail.subject.clone());
+
+        let text = saved
+            .mail
+            .text_message
+            .clone()
+            .unwrap_or_else(|| html_to_text(&saved.mail.message));
+        let alternative = MultiPart::alternative_plain_html(text, saved.mail.message.clone());
+
+        let email = match attachments.is_empty() {
+            true => builder.multipart(alternative),
+            false => {
+                let mut multipart = MultiPart::mixed().multipart(alternative);
+                for attachment in attachments {
+                    let content_type =
+                        ContentType::parse(&attachment.content_type).unwrap_or_else(|_| {
+                            ContentType::parse("application/octet-stream").unwrap()
+                        });
+                    multipart = multipart.singlepart(
+                        Attachment::new(attachment.file_name)
+                            .body(attachment.content, content_type),
+                    );
+                }
+
+                builder.multipart(multipart)
+            }
+        }
+        .unwrap();
+
+        let transports = match SmtpRelayService.transports(app, saved.mail.application_id) {
+            Ok(transports) => transports,
+            Err(err) => {
+                error!(
+                    "[{}] Failed to load smtp relays of mail #{}, [error: {}], re-queueing...",
+                    thread_name, subject, err
+                );
+                let _ = self.push_to_failure_notification_queue(
+                    app,
+                    MailFailureResponse {
+                        saved_mail: saved.clone(),
+                        error_message: err.to_string(),
+                        smtp: SmtpErrorDetail::default(),
+                    },
+                );
+                return;
+            }
+        };
+
+        let envelope = self.make_envelope(app, &saved.mail, &email);
+        let raw = email.formatted();
+
+        // relays are attempted in order, a permanent rejection is not retried on the next relay
+        let mut result = None;
+        for (name, transport) in transports {
+            match transport.send_raw(&envelope, &raw).await {
+                Ok(resp) => {
+                    result = Some(Ok(resp));
+                    break;
+                }
+                Err(err) => {
+                    let permanent = MailErrorService.classify(&err).permanent;
+                    warn!(
+                        "[{}] Relay '{}' failed to send mail #{}, [error: {}]",
+                        thread_name, name, subject, err
+                    );
+                    result = Some(Err(err));
+                    if permanent {
+                        break;
+                    }
+                }
+            }
+        }
 
-        match smtp.send(&email) {
-            Ok(_resp) => {
+        match result.unwrap() {
+            Ok(resp) => {
+                let response_body = resp.message().collect::<Vec<&str>>().join("\n");
+                let queue_id = self.parse_queue_id(&response_body);
                 let _ = self.push_to_success_notification_queue(
                     app,
                     MailSuccessResponse {
-                        response_body: String::from("sent"),
+                        response_code: resp.code().to_string().parse().ok(),
+                        queue_id,
+                        message_id: Some(message_id),
+                        response_body,
                         saved_mail: saved.clone(),
                     },
                 );
@@ -184,12 +978,61 @@ impl MailService {
                     MailFailureResponse {
                         saved_mail: saved.clone(),
                         error_message: err.to_string(),
+                        smtp: MailErrorService.classify(&err),
                     },
                 );
             }
         };
     }
 
+    /// Envelope of the message, its sender is the mail's VERP return path when a bounce domain is set
+    fn make_envelope(&mut self, app: &AppState, mail: &Mail, email: &Message) -> Envelope {
+        let return_path = BounceService
+            .return_path(app, mail.mail_id)
+            .and_then(|address| address.parse::<Address>().ok());
+
+        match return_path {
+            Some(return_path) => Envelope::new(Some(return_path), email.envelope().to().to_vec())
+                .unwrap_or_else(|_| email.envelope().clone()),
+            None => email.envelope().clone(),
+        }
+    }
+
+    /// Message-ID is derived from the mail id so that a message can be traced back
+    /// from the relay's logs, it stays the same across retries
+    fn make_message_id(&mut self, mail: &Mail) -> String {
+        let domain = mail
+            .from_email
+            .rsplit_once('@')
+            .map(|(_, domain)| domain)
+            .unwrap_or("localhost");
+
+        format!("<{}@{}>", mail.mail_id, domain)
+    }
+
+    /// Extracts the queue id relays report on acceptance,
+    /// e.g. postfix `2.0.0 Ok: queued as 4Bz1xK3Jq7z9` or exim `OK id=1rS2xY-0001aB-2c`
+    fn parse_queue_id(&mut self, response: &str) -> Option<String> {
+        let clean = |id: &str| {
+            id.trim_matches(|c: char| c == '<' || c == '>' || c == '.')
+                .to_string()
+        };
+
+        if let Some(index) = response.to_ascii_lowercase().find("queued as ") {
+            return response[index + "queued as ".len()..]
+                .split_whitespace()
+                .next()
+                .map(clean)
+                .filter(|id| !id.is_empty());
+        }
+
+        response
+            .split_whitespace()
+            .find_map(|token| token.strip_prefix("id="))
+            .map(clean)
+            .filter(|id| !id.is_empty())
+    }
+
     pub fn push_to_awaiting_queue(
         &mut self,
         app: &AppState,
@@ -203,11 +1046,95 @@ impl MailService {
         app: &AppState,
         saved: MailSaved,
     ) -> RedisResult<i32> {
-        self.push_to_queue(app, app.redis_queues.processing.clone(), saved)
+        let queue = self.processing_queue(app, &saved.mail);
+        let pushed = self.push_to_queue(app, queue, saved)?;
+
+        // the mail is queued already, an idle worker not being woken up only delays it
+        if let Err(err) = QueueService.signal(app) {
+            error!(
+                "[push_to_processing_queue] failed to signal workers: {:?}",
+                err
+            );
+        }
+
+        Ok(pushed)
     }
 
+    /// Holds the mail back until `next_retrial_at`, it is then moved to the processing queue
     pub fn push_to_retrying_queue(&mut self, app: &AppState, saved: MailSaved) -> RedisResult<i32> {
-        self.push_to_queue(app, app.redis_queues.retrying.clone(), saved)
+        let due = saved.mail.next_retrial_at.unwrap_or_else(current_timestamp);
+        let queue = self.processing_queue(app, &saved.mail);
+        self.push_to_delayed_queue(app, queue, saved, due)
+    }
+
+    /// Holds the mail back until `send_at`, it is then moved to the processing queue
+    pub fn push_to_scheduled_queue(
+        &mut self,
+        app: &AppState,
+        saved: MailSaved,
+    ) -> RedisResult<i32> {
+        let due = saved.mail.send_at.unwrap_or_else(current_timestamp);
+        let queue = self.processing_queue(app, &saved.mail);
+        self.push_to_delayed_queue(app, queue, saved, due)
+    }
+
+    /// Processing list the mail is sent from, according to its priority
+    pub fn processing_queue(&mut self, app: &AppState, mail: &Mail) -> String {
+        let priority = MailPriority::from_str(&mail.priority).unwrap_or_default();
+        app.redis_queues.processing_of(priority)
+    }
+
+    /// Order in which processing lists are checked, high priority mails are always taken first,
+    /// normal & bulk ones take turns according to their weights so bulk mails are not starved
+    pub fn processing_order(&mut self, app: &AppState) -> Vec<MailPriority> {
+        let total = app.normal_queue_weight + app.bulk_queue_weight;
+        let bulk_first = total > 0 && rand::rng().random_range(0..total) < app.bulk_queue_weight;
+
+        match bulk_first {
+            true => vec![MailPriority::High, MailPriority::Bulk, MailPriority::Normal],
+            false => vec![MailPriority::High, MailPriority::Normal, MailPriority::Bulk],
+        }
+    }
+
+    pub fn push_to_delayed_queue<T: Serialize>(
+        &mut self,
+        app: &AppState,
+        queue: String,
+        data: T,
+        due: NaiveDateTime,
+    ) -> RedisResult<i32> {
+        let item = DelayedQueueItem {
+            queue,
+            payload: serde_json::to_string(&data).unwrap(),
+        };
+
+        app.clone().redis.zadd::<&str, i64, String, i32>(
+            &app.redis_queues.retrying,
+            serde_json::to_string(&item).unwrap(),
+            due.and_utc().timestamp(),
+        )
+    }
+
+    /// Moves delayed items that are due onto their queues, in a single script so an item
+    /// is never left out of both the set and its queue, nor moved by two workers
+    pub fn release_due_items(&mut self, app: &AppState, limit: isize) -> RedisResult<usize> {
+        let now = current_timestamp().and_utc().timestamp();
+        let (released, invalid) = redis::cmd("EVAL")
+            .arg(RELEASE_DUE_ITEMS_SCRIPT)
+            .arg(1)
+            .arg(&app.redis_queues.retrying)
+            .arg(now)
+            .arg(limit)
+            .query::<(usize, usize)>(&mut app.redis.clone())?;
+
+        if invalid > 0 {
+            error!(
+                "[release_due_items] dropped {} invalid delayed item(s)",
+                invalid
+            );
+        }
+
+        Ok(released)
     }
 
     pub fn push_to_failure_notification_queue(
@@ -226,8 +1153,12 @@ impl MailService {
         self.push_to_queue(app, app.redis_queues.success.clone(), data)
     }
 
-    pub fn push_to_callback_queue(&mut self, app: &AppState, impulse: Mail) -> RedisResult<i32> {
-        self.push_to_queue(app, app.redis_queues.callback.clone(), impulse)
+    pub fn push_to_callback_queue(
+        &mut self,
+        app: &AppState,
+        payload: MailCallbackPayload,
+    ) -> RedisResult<i32> {
+        self.push_to_queue(app, app.redis_queues.callback.clone(), payload)
     }
 
     fn push_to_queue<T: Serialize>(
@@ -242,3 +1173,92 @@ impl MailService {
             .lpush::<&str, &str, i32>(&*queue, json.as_str())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doubles_the_retry_delay_per_trial() {
+        let delays: Vec<i64> = (1..=5)
+            .map(|trials| MailService::retry_delay_bounds(60, 3600, 0, trials).0)
+            .collect();
+        assert_eq!(delays, vec![60, 120, 240, 480, 960]);
+    }
+
+    #[test]
+    fn caps_the_retry_delay() {
+        assert_eq!(MailService::retry_delay_bounds(60, 3600, 0, 7), (3600, 0));
+        assert_eq!(
+            MailService::retry_delay_bounds(60, 3600, 0, i16::MAX),
+            (3600, 0)
+        );
+        assert_eq!(
+            MailService::retry_delay_bounds(i64::MAX, i64::MAX, 0, 20),
+            (i64::MAX, 0)
+        );
+    }
+
+    #[test]
+    fn treats_missing_trials_as_the_first() {
+        assert_eq!(MailService::retry_delay_bounds(60, 3600, 0, 0), (60, 0));
+        assert_eq!(MailService::retry_delay_bounds(60, 3600, 0, -3), (60, 0));
+    }
+
+    #[test]
+    fn bounds_the_jitter_by_percent() {
+        assert_eq!(MailService::retry_delay_bounds(60, 3600, 10, 2), (120, 12));
+        assert_eq!(MailService::retry_delay_bounds(60, 3600, -10, 2), (120, 0));
+        assert_eq!(MailService::retry_delay_bounds(5, 3600, 10, 1), (5, 0));
+
+        let (delay, jitter) = MailService::retry_delay_bounds(i64::MAX, i64::MAX, 50, 1);
+        assert!(delay.checked_add(jitter).is_some());
+    }
+
+    #[test]
+    fn never_returns_a_negative_delay() {
+        assert_eq!(MailService::retry_delay_bounds(-60, 3600, 10, 3), (0, 0));
+        assert_eq!(MailService::retry_delay_bounds(60, -1, 10, 3), (0, 0));
+    }
+
+    #[test]
+    fn reads_the_queue_id_relays_report() {
+        for (response, queue_id) in [
+            ("2.0.0 Ok: queued as 4Bz1xK3Jq7z9", "4Bz1xK3Jq7z9"),
+            ("250 2.0.0 Ok: queued as <4Bz1xK3Jq7z9>.", "4Bz1xK3Jq7z9"),
+            ("250 2.0.0 Message Queued As ABC123 for delivery", "ABC123"),
+            ("250 OK id=1rS2xY-0001aB-2c", "1rS2xY-0001aB-2c"),
+        ] {
+            assert_eq!(
+                MailService.parse_queue_id(response).as_deref(),
+                Some(queue_id),
+                "{}",
+                response
+            );
+        }
+    }
+
+    #[test]
+    fn reports_no_queue_id_when_there_is_none() {
+        for response in [
+            "",
+            "250 2.0.0 OK 1700000000 x1si123 - gsmtp",
+            "250 2.0.0 Ok: queued as ",
+            "250 2.0.0 Ok: queued as <>",
+            "250 OK id=",
+            "250 OK grid=",
+        ] {
+            assert_eq!(MailService.parse_queue_id(response), None, "{}", response);
+        }
+    }
+
+    #[test]
+    fn reads_queue_ids_next_to_multibyte_text() {
+        assert_eq!(
+            MailService
+                .parse_queue_id("250 Ünïcödé ok: queued as 4Bz1xK3Jq7z9")
+                .as_deref(),
+            Some("4Bz1xK3Jq7z9")
+        );
+    }
+}
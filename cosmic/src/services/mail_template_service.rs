@@ -0,0 +1,258 @@
+use std::borrow::Cow;
+use std::error::Error;
+
+use tera::Context;
+use uuid::Uuid;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::app_state::AppState;
+use crate::enums::app_message::AppMessage;
+use crate::helpers::html::html_to_text;
+use crate::helpers::template::render_untrusted;
+use crate::helpers::DBPool;
+use crate::models::mail_template::{
+    MailTemplate, MailTemplateCreateForm, MailTemplatePreviewForm, MailTemplateRef,
+    MailTemplateUpdateForm, RenderedTemplate,
+};
+use crate::repositories::mail_template_repository::MailTemplateRepository;
+use crate::results::AppResult;
+use crate::services::mailer_service::MailerService;
+
+pub struct MailTemplateService;
+
+impl MailTemplateService {
+    pub fn create(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        created_by: Uuid,
+        form: MailTemplateCreateForm,
+    ) -> AppResult<MailTemplate> {
+        if MailTemplateRepository.exists(pool, app_id, form.name.clone())? {
+            let msg = format!("Mail template({}) already exists", form.name);
+            return Err(AppMessage::WarningMessage(msg));
+        }
+
+        // a name that was deleted before continues its numbering, the old versions are still there
+        let version = MailTemplateRepository.max_version(pool, app_id, form.name.clone())? + 1;
+        MailTemplateRepository.create(pool, app_id, created_by, version, form)
+    }
+
+    pub fn update(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        created_by: Uuid,
+        name: String,
+        form: MailTemplateUpdateForm,
+    ) -> AppResult<MailTemplate> {
+        let latest = MailTemplateRepository.find_latest(pool, app_id, name.clone())?;
+        MailTemplateRepository.create_next_version(
+            pool,
+            latest,
+            created_by,
+            MailTemplateCreateForm {
+                name,
+                subject: form.subject,
+                html: form.html,
+                text: form.text,
+            },
+        )
+    }
+
+    pub fn find(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        name: String,
+        version: Option<i32>,
+    ) -> AppResult<MailTemplate> {
+        match version {
+            Some(version) => MailTemplateRepository.find_version(pool, app_id, name, version),
+            None => MailTemplateRepository.find_latest(pool, app_id, name),
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        app: &AppState,
+        app_id: Uuid,
+        template: MailTemplateRef,
+    ) -> AppResult<RenderedTemplate> {
+        let found = self.find(app.database(), app_id, template.name, template.version)?;
+        let mut context = Self::make_context(template.variables)?;
+        MailerService::insert_globals(app, &mut context);
+
+        let render = |part: &str, source: &str| -> AppResult<String> {
+            // html parts are auto-escaped by tera, subject & text are rendered as-is
+            let name = format!("{}.v{}.{}", found.name, found.version, part);
+            app.render_raw(&name, source, &context)
+                .map_err(|err| AppMessage::WarningMessage(Self::error_message(&err)))
+        };
+
+        Ok(RenderedTemplate {
+            subject: render("subject.txt", &found.subject)?,
+            html: render("html", &found.html)?,
+            text: match &found.text {
+                Some(text) => Some(render("txt", text)?),
+                None => None,
+            },
+        })
+    }
+
+    /// Renders the given template the way a mail would be, with the mailer's globals available,
+    /// render errors are reported per part (subject, html, text) along with their line & column
+    pub fn preview(
+        &mut self,
+        app: &AppState,
+        mut form: MailTemplatePreviewForm,
+    ) -> AppResult<RenderedTemplate> {
+        let mut context = Self::make_context(std::mem::take(&mut form.variables))?;
+        MailerService::insert_globals(app, &mut context);
+
+        Self::render_preview(form, &context, |file, context| app.render(file, context))
+    }
+
+    /// Submitted sources are rendered like stored templates (see `render_untrusted`),
+    /// bundled templates are rendered with `render_file`
+    fn render_preview(
+        form: MailTemplatePreviewForm,
+        context: &Context,
+        render_file: impl FnOnce(String, &Context) -> tera::Result<String>,
+    ) -> AppResult<RenderedTemplate> {
+        let mut errors = ValidationErrors::new();
+        let mut render = |field: &'static str, source: &str| {
+            let name = format!("preview.{}", field);
+            render_untrusted(&name, source, context)
+                .map_err(|err| errors.add(field, Self::validation_error(&err)))
+                .ok()
+        };
+
+        let subject = render("subject", &form.subject.unwrap_or_default());
+        let text = form.text.map(|text| render("text", &text));
+        let html = match (form.template, form.html) {
+            (Some(file), _) => render_file(file, context)
+                .map_err(|err| errors.add("template", Self::validation_error(&err)))
+                .ok(),
+            (None, Some(html)) => render("html", &html),
+            (None, None) => {
+                return Err(AppMessage::WarningMessageStr(
+                    "either html or template is required",
+                ));
+            }
+        };
+
+        match (subject, html, text) {
+            (Some(subject), Some(html), None) => Ok(RenderedTemplate {
+                subject,
+                text: Some(html_to_text(&html)),
+                html,
+            }),
+            (Some(subject), Some(html), Some(Some(text))) => Ok(RenderedTemplate {
+                subject,
+                html,
+                text: Some(text),
+            }),
+            _ => Err(AppMessage::FormValidationError(errors)),
+        }
+    }
+
+    fn make_context(variables: serde_json::Value) -> AppResult<Context> {
+        match variables {
+            serde_json::Value::Null => Ok(Context::new()),
+            variables => Context::from_value(variables)
+                .map_err(|_| AppMessage::WarningMessageStr("template variables must be an object")),
+        }
+    }
+
+    fn validation_error(error: &tera::Error) -> ValidationError {
+        let message = Self::error_message(error);
+        let mut validation = ValidationError::new("template");
+        if let Some((line, column)) = Self::error_location(&message) {
+            validation.add_param(Cow::Borrowed("line"), &line);
+            validation.add_param(Cow::Borrowed("column"), &column);
+        }
+
+        validation.message = Some(Cow::Owned(message));
+        validation
+    }
+
+    /// syntax errors point at the offending position as ` --> line:column`
+    fn error_location(message: &str) -> Option<(usize, usize)> {
+        let (_, position) = message.split_once("--> ")?;
+        let position = position.split_whitespace().next()?;
+        let (line, column) = position.split_once(':')?;
+        Some((line.parse().ok()?, column.parse().ok()?))
+    }
+
+    /// tera keeps the actual cause (missing variable, syntax error...) in the error's source chain
+    fn error_message(error: &tera::Error) -> String {
+        let mut message = error.to_string();
+        let mut source = error.source();
+        while let Some(err) = source {
+            message.push_str(&format!(": {}", err));
+            source = err.source();
+        }
+
+        message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form(subject: &str, html: &str) -> MailTemplatePreviewForm {
+        MailTemplatePreviewForm {
+            template: None,
+            subject: Some(subject.to_string()),
+            html: Some(html.to_string()),
+            text: None,
+            variables: serde_json::Value::Null,
+        }
+    }
+
+    fn no_bundled_template(_: String, _: &Context) -> tera::Result<String> {
+        panic!("no bundled template was asked for")
+    }
+
+    #[test]
+    fn previews_submitted_sources() {
+        let mut context = Context::new();
+        context.insert("name", "John");
+
+        let rendered = MailTemplateService::render_preview(
+            form("Hi {{ name }}", "<p>Hello {{ name }}</p>"),
+            &context,
+            no_bundled_template,
+        )
+        .unwrap_or_else(|_| panic!("preview failed"));
+
+        assert_eq!(rendered.subject, "Hi John");
+        assert_eq!(rendered.html, "<p>Hello John</p>");
+        assert_eq!(rendered.text.as_deref(), Some("Hello John"));
+    }
+
+    #[test]
+    fn previews_cannot_read_the_environment() {
+        let leak = r#"{{ get_env(name="PATH") }}"#;
+        for (form, field) in [
+            (form(leak, "<p>Hello</p>"), "subject"),
+            (form("Hi", leak), "html"),
+            (
+                MailTemplatePreviewForm {
+                    text: Some(leak.to_string()),
+                    ..form("Hi", "<p>Hello</p>")
+                },
+                "text",
+            ),
+        ] {
+            match MailTemplateService::render_preview(form, &Context::new(), no_bundled_template) {
+                Err(AppMessage::FormValidationError(errors)) => {
+                    assert!(errors.field_errors().contains_key(field), "{}", field)
+                }
+                _ => panic!("{} read the environment", field),
+            }
+        }
+    }
+}
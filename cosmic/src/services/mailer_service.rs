@@ -11,7 +11,7 @@ use tokio::spawn;
 use uuid::Uuid;
 
 use crate::app_state::AppState;
-use crate::models::mail::{MailBox, MailQueueablePayload};
+use crate::models::mail::{MailBox, MailPriority, MailQueueablePayload};
 use crate::results::RedisResult;
 use crate::services::mail_service::MailService;
 
@@ -49,6 +49,8 @@ pub struct MailerService {
     from: MailBox,
     subject: String,
     message: String,
+    text: Option<String>,
+    priority: MailPriority,
 }
 
 impl MailerService {
@@ -61,6 +63,8 @@ impl MailerService {
             reply_to: vec![],
             receiver: vec![],
             message: String::from(""),
+            text: None,
+            priority: MailPriority::Normal,
             subject: String::from(""),
             from: MailBox {
                 name: env::var("MAILER_MAIL_FROM_NAME").unwrap(),
@@ -89,15 +93,40 @@ impl MailerService {
         self
     }
 
-    pub fn view(&mut self, file: &str, mut ctx: Context) -> &mut MailerService {
-        ctx.insert("year", &Utc::now().year());
-        ctx.insert("app_name", &self.app.app_name.clone());
-        ctx.insert("app_desc", &self.app.app_desc.clone());
-        ctx.insert("app_logo_url", &self.app.app_logo_url.clone());
-        ctx.insert("app_help_email", &self.app.app_help_email.clone());
-        ctx.insert("app_frontend_url", &self.app.app_frontend_url.clone());
+    pub fn text(&mut self, t: Option<String>) -> &mut MailerService {
+        self.text = t;
+        self
+    }
+
+    pub fn priority(&mut self, p: MailPriority) -> &mut MailerService {
+        self.priority = p;
+        self
+    }
+
+    pub fn view(&mut self, file: &str, mut ctx: Context) -> tera::Result<&mut MailerService> {
+        Self::insert_globals(&self.app, &mut ctx);
+        let body = self.app.render(file.to_string(), &ctx)?;
+        Ok(self.body(body))
+    }
 
-        self.body(self.app.render(file.to_string(), ctx))
+    /// Same as `view`, but logs the render error and sends `fallback` as a plain message instead
+    pub fn view_or(&mut self, file: &str, ctx: Context, fallback: String) -> &mut MailerService {
+        if let Err(err) = self.view(file, ctx) {
+            error!("failed to render \"{}\" mail template: {:?}", file, err);
+            self.body(fallback.clone()).text(Some(fallback));
+        }
+
+        self
+    }
+
+    /// Variables every template rendered by the mailer has access to
+    pub fn insert_globals(app: &AppState, ctx: &mut Context) {
+        ctx.insert("year", &Utc::now().year());
+        ctx.insert("app_name", &app.app_name.clone());
+        ctx.insert("app_desc", &app.app_desc.clone());
+        ctx.insert("app_logo_url", &app.app_logo_url.clone());
+        ctx.insert("app_help_email", &app.app_help_email.clone());
+        ctx.insert("app_frontend_url", &app.app_frontend_url.clone());
     }
 
     pub fn send_silently(&mut self) {
@@ -129,20 +158,32 @@ impl MailerService {
     }
 
     async fn do_send(&self) -> RedisResult<i32> {
+        self.queue()
+    }
+
+    /// Pushes the mail to the awaiting queue without retrying
+    pub fn queue(&self) -> RedisResult<i32> {
         let user_id = Uuid::from_str(self.app.mailer_system_user_id.as_str()).unwrap();
         let app_id = Uuid::from_str(self.app.mailer_application_id.as_str()).unwrap();
         MailService.push_to_awaiting_queue(
             self.app.as_ref(),
             MailQueueablePayload {
+                mail_id: Uuid::new_v4(),
+                reference: None,
                 application_id: app_id,
                 created_by: user_id,
                 subject: self.subject.clone(),
                 message: self.message.clone(),
+                text: self.text.clone(),
                 from: Some(self.from.clone()),
                 cc: self.cc.clone(),
                 bcc: self.bcc.clone(),
                 reply_to: self.reply_to.clone(),
                 receiver: self.receiver.clone(),
+                attachments: vec![],
+                template: None,
+                send_at: None,
+                priority: self.priority,
             },
         )
     }
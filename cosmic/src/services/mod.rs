@@ -3,22 +3,30 @@ pub mod app_key_service;
 pub mod application_service;
 pub mod auth_attempt_service;
 pub mod auth_service;
+pub mod bounce_service;
 pub mod cache_service;
 pub mod file_upload_service;
 pub mod mail_address_service;
+pub mod mail_attachment_service;
 pub mod mail_error_service;
 pub mod mail_service;
+pub mod mail_template_service;
 pub mod mailer_service;
 pub mod notification_service;
 pub mod password_reset_service;
 pub mod permission_service;
 pub mod personal_access_token_service;
+pub mod queue_service;
+pub mod rate_limit_service;
 pub mod redis_next_service;
 pub mod redis_service;
 pub mod role_permission_service;
 pub mod role_service;
+pub mod smtp_relay_service;
+pub mod suppression_service;
 pub mod ui_menu_item_service;
 pub mod ui_menu_service;
 pub mod user_permission_service;
 pub mod user_service;
 pub mod user_ui_menu_item_service;
+pub mod webhook_service;
@@ -7,7 +7,7 @@ use crate::app_state::AppState;
 use crate::helpers::db::DatabaseConnectionHelper;
 use crate::helpers::time::current_timestamp;
 use crate::helpers::DBPool;
-use crate::models::mail::MailBox;
+use crate::models::mail::{MailBox, MailPriority};
 use crate::models::password_reset::{PasswordReset, PasswordResetCreateDto, PasswordResetStatus};
 use crate::models::user::{FullName, PasswordForm, User};
 use crate::repositories::password_reset_repository::PasswordResetRepository;
@@ -38,11 +38,19 @@ impl PasswordResetService {
         context.insert("full_name", &user.full_name());
         context.insert("token", &token);
 
+        let fallback = format!(
+            "Hi {}, use the link below to reset your password\n{}/reset-password?token={}",
+            user.full_name(),
+            app.app_frontend_url,
+            token
+        );
+
         let subject = app.title("Password Reset");
         MailerService::new(app)
+            .priority(MailPriority::High)
             .subject(subject)
             .receivers(vec![MailBox::new(&user.full_name(), user.email.as_str())])
-            .view("password-reset", context)
+            .view_or("password-reset", context, fallback)
             .send_silently();
 
         Ok(reset)
@@ -64,11 +72,17 @@ impl PasswordResetService {
         let mut context = Context::new();
         context.insert("full_name", &user.full_name());
 
+        let fallback = format!(
+            "Hi {}, your password has been changed successfully",
+            user.full_name()
+        );
+
         let subject = app.title("Changed Password");
         MailerService::new(app)
+            .priority(MailPriority::High)
             .subject(subject)
             .receivers(vec![MailBox::new(&user.full_name(), user.email.as_str())])
-            .view("password-reset-success", context)
+            .view_or("password-reset-success", context, fallback)
             .send_silently();
 
         Ok(user)
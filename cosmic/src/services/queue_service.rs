@@ -0,0 +1,251 @@
+use log::{error, info};
+use redis::{Commands, Direction, ExistenceCheck, SetExpiry, SetOptions};
+
+use crate::app_state::AppState;
+use crate::models::mail::InFlightQueue;
+use crate::results::{AppResult, RedisResult};
+
+/// set holding every in-flight list, so lists of dead workers can be found
+const IN_FLIGHT_REGISTRY: &str = "executor:in-flight";
+const HEARTBEAT_KEY_PREFIX: &str = "executor:heartbeat";
+/// set of each worker's in-flight lists, re-added to the registry on every heartbeat
+const CONSUMERS_KEY_PREFIX: &str = "executor:consumers";
+/// when the reaper first found a worker's heartbeat missing
+const MISSING_KEY_PREFIX: &str = "executor:missing";
+/// how long a pop waits for an item before returning None
+const POP_TIMEOUT_SECONDS: f64 = 5.0;
+/// signals kept for idle workers, pushes beyond that would only wake them up for nothing
+const MAX_SIGNALS: isize = 100;
+
+/// Reliable consumption of the mail queues, an item is moved to the consumer's in-flight list
+/// when popped and only removed from there once acknowledged, items left behind by
+/// a worker that stopped sending heartbeats are moved back onto their queue by `reap`
+pub struct QueueService;
+
+impl QueueService {
+    /// Records the consumer's in-flight list, must be called before popping from `queue`
+    pub fn register(
+        &mut self,
+        app: &AppState,
+        queue: &str,
+        worker_id: &str,
+        consumer: &str,
+    ) -> RedisResult<InFlightQueue> {
+        let in_flight = InFlightQueue {
+            queue: queue.to_string(),
+            list: format!("{}:in-flight:{}", queue, consumer),
+            worker_id: worker_id.to_string(),
+        };
+
+        let member = serde_json::to_string(&in_flight).unwrap();
+        redis::pipe()
+            .atomic()
+            .sadd(IN_FLIGHT_REGISTRY, &member)
+            .ignore()
+            .sadd(format!("{}:{}", CONSUMERS_KEY_PREFIX, worker_id), &member)
+            .ignore()
+            .query::<()>(&mut app.redis.clone())?;
+
+        self.heartbeat(app, worker_id)?;
+
+        Ok(in_flight)
+    }
+
+    /// Waits for the next item of the queue, parking it on the consumer's in-flight list
+    pub async fn pop(
+        &mut self,
+        app: &AppState,
+        in_flight: &InFlightQueue,
+    ) -> AppResult<Option<String>> {
+        app.services
+            .redis_next
+            .blmove(
+                &in_flight.queue,
+                &in_flight.list,
+                Direction::Right,
+                Direction::Left,
+                POP_TIMEOUT_SECONDS,
+            )
+            .await
+    }
+
+    /// Takes the next item of the first non-empty queue, returning its position along with it.
+    /// When all of them are empty, waits for a push to any of them on the processing signal,
+    /// items are still only ever moved with LMOVE so none is lost between lists
+    pub async fn pop_first(
+        &mut self,
+        app: &AppState,
+        in_flights: &[&InFlightQueue],
+    ) -> AppResult<Option<(usize, String)>> {
+        if let Some(popped) = self.take_first(app, in_flights).await? {
+            return Ok(Some(popped));
+        }
+
+        let signal = app.redis_queues.processing_signal();
+        match app
+            .services
+            .redis_next
+            .blpop(&signal, POP_TIMEOUT_SECONDS)
+            .await?
+        {
+            Some(_) => self.take_first(app, in_flights).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn take_first(
+        &mut self,
+        app: &AppState,
+        in_flights: &[&InFlightQueue],
+    ) -> AppResult<Option<(usize, String)>> {
+        let redis = &app.services.redis_next;
+        for (position, in_flight) in in_flights.iter().enumerate() {
+            let item = redis
+                .lmove(
+                    &in_flight.queue,
+                    &in_flight.list,
+                    Direction::Right,
+                    Direction::Left,
+                )
+                .await?;
+
+            if let Some(item) = item {
+                return Ok(Some((position, item)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Wakes up a worker waiting in `pop_first`
+    pub fn signal(&mut self, app: &AppState) -> RedisResult<()> {
+        let signal = app.redis_queues.processing_signal();
+        redis::pipe()
+            .lpush(&signal, 1)
+            .ignore()
+            .ltrim(&signal, 0, MAX_SIGNALS - 1)
+            .ignore()
+            .query::<()>(&mut app.redis.clone())
+    }
+
+    /// Removes a handled item from the in-flight list
+    pub fn ack(
+        &mut self,
+        app: &AppState,
+        in_flight: &InFlightQueue,
+        item: &str,
+    ) -> RedisResult<i32> {
+        app.redis
+            .clone()
+            .lrem::<&str, &str, i32>(&in_flight.list, 1, item)
+    }
+
+    /// Puts an item that could not be handled back at the head of its queue
+    pub fn requeue(
+        &mut self,
+        app: &AppState,
+        in_flight: &InFlightQueue,
+        item: &str,
+    ) -> RedisResult<()> {
+        self.replace(app, in_flight, item, item)
+    }
+
+    /// Puts an updated version of an in-flight item back at the head of its queue
+    pub fn replace(
+        &mut self,
+        app: &AppState,
+        in_flight: &InFlightQueue,
+        item: &str,
+        replacement: &str,
+    ) -> RedisResult<()> {
+        redis::pipe()
+            .atomic()
+            .lrem(&in_flight.list, 1, item)
+            .ignore()
+            .rpush(&in_flight.queue, replacement)
+            .ignore()
+            .query::<()>(&mut app.redis.clone())
+    }
+
+    /// Marks the worker as alive & puts its consumers back into the registry,
+    /// in case the reaper gave up on them while heartbeats were late
+    pub fn heartbeat(&mut self, app: &AppState, worker_id: &str) -> RedisResult<()> {
+        let mut redis = app.redis.clone();
+        let consumers = format!("{}:{}", CONSUMERS_KEY_PREFIX, worker_id);
+        let members = redis.smembers::<&str, Vec<String>>(&consumers)?;
+
+        let mut pipe = redis::pipe();
+        pipe.set_ex(
+            format!("{}:{}", HEARTBEAT_KEY_PREFIX, worker_id),
+            chrono::Utc::now().timestamp(),
+            app.worker_heartbeat_ttl,
+        )
+        .ignore()
+        .expire(&consumers, (app.worker_heartbeat_ttl * 3) as i64)
+        .ignore();
+
+        if !members.is_empty() {
+            pipe.sadd(IN_FLIGHT_REGISTRY, members).ignore();
+        }
+
+        pipe.query::<()>(&mut redis)
+    }
+
+    /// Moves items of in-flight lists whose worker is no longer alive back onto their queues
+    pub fn reap(&mut self, app: &AppState) -> RedisResult<usize> {
+        let mut redis = app.redis.clone();
+        let members = redis.smembers::<&str, Vec<String>>(IN_FLIGHT_REGISTRY)?;
+
+        let mut requeued = 0;
+        for member in members {
+            let in_flight = match serde_json::from_str::<InFlightQueue>(&member) {
+                Ok(in_flight) => in_flight,
+                Err(err) => {
+                    error!("[reap] invalid in-flight entry: {:?}", err);
+                    redis.srem::<&str, &str, i32>(IN_FLIGHT_REGISTRY, &member)?;
+                    continue;
+                }
+            };
+
+            let heartbeat = format!("{}:{}", HEARTBEAT_KEY_PREFIX, in_flight.worker_id);
+            let missing = format!("{}:{}", MISSING_KEY_PREFIX, in_flight.worker_id);
+            if redis.exists::<String, bool>(heartbeat)? {
+                redis.del::<&str, i32>(&missing)?;
+                continue;
+            }
+
+            // a late heartbeat is not a dead worker, it is only given up on once
+            // its heartbeat has been missing for another heartbeat ttl
+            let now = chrono::Utc::now().timestamp();
+            let options = SetOptions::default()
+                .conditional_set(ExistenceCheck::NX)
+                .with_expiration(SetExpiry::EX(app.worker_heartbeat_ttl * 3));
+            redis.set_options::<&str, i64, Option<String>>(&missing, now, options)?;
+            let missing_since = redis.get::<&str, Option<i64>>(&missing)?.unwrap_or(now);
+            if now - missing_since < app.worker_heartbeat_ttl as i64 {
+                continue;
+            }
+
+            while redis
+                .lmove::<&str, &str, Option<String>>(
+                    &in_flight.list,
+                    &in_flight.queue,
+                    Direction::Right,
+                    Direction::Right,
+                )?
+                .is_some()
+            {
+                requeued += 1;
+            }
+
+            info!("[reap] released in-flight list: {}", in_flight.list);
+            redis.srem::<&str, &str, i32>(IN_FLIGHT_REGISTRY, &member)?;
+        }
+
+        if requeued > 0 {
+            self.signal(app)?;
+        }
+
+        Ok(requeued)
+    }
+}
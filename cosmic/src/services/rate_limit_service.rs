@@ -0,0 +1,274 @@
+use chrono::{Duration, Utc};
+use log::error;
+use redis::Commands;
+use uuid::Uuid;
+
+use crate::app_state::{AppState, DomainLimit};
+use crate::enums::app_message::AppMessage;
+use crate::helpers::time::current_timestamp;
+use crate::models::application::Application;
+use crate::models::mail::{MailSaved, ThrottleDecision};
+use crate::repositories::application_repository::ApplicationRepository;
+use crate::results::AppResult;
+use crate::services::mail_service::MailService;
+
+const RATE_LIMIT_KEY_PREFIX: &str = "rate-limit";
+const THROTTLE_KEY_PREFIX: &str = "throttle";
+const SENDING_KEY_PREFIX: &str = "sending:domain";
+/// how long a mail waits when every sending slot of one of its domains is taken
+const DOMAIN_BUSY_DELAY_MILLIS: u64 = 2000;
+
+/// Adds ARGV[1] to the counter of each window (KEYS) unless one of them would go past its limit,
+/// ARGV holds the limit & length in seconds of each window after that,
+/// returns the position of the exceeded window or 0 once counted
+const RESERVE_SCRIPT: &str = r#"
+local count = tonumber(ARGV[1])
+for i, key in ipairs(KEYS) do
+    if tonumber(redis.call('GET', key) or '0') + count > tonumber(ARGV[i * 2]) then
+        return i
+    end
+end
+for i, key in ipairs(KEYS) do
+    redis.call('INCRBY', key, count)
+    redis.call('EXPIRE', key, ARGV[i * 2 + 1])
+end
+return 0
+"#;
+
+/// Takes one of the ARGV[2] sending slots of the domain (KEYS[1]) for the mail ARGV[1],
+/// slots held for longer than ARGV[3] seconds are taken back first, as their worker likely died
+const ACQUIRE_SLOT_SCRIPT: &str = r#"
+local ttl = tonumber(ARGV[3])
+local now = tonumber(redis.call('TIME')[1])
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now - ttl)
+if not redis.call('ZSCORE', KEYS[1], ARGV[1])
+    and redis.call('ZCARD', KEYS[1]) >= tonumber(ARGV[2]) then
+    return 0
+end
+redis.call('ZADD', KEYS[1], now, ARGV[1])
+redis.call('EXPIRE', KEYS[1], ttl)
+return 1
+"#;
+
+/// Token bucket refilled at ARGV[1] tokens per second up to ARGV[2], timed by the redis clock so
+/// workers do not need to agree on the time, returns how many milliseconds to wait when empty
+const TAKE_TOKEN_SCRIPT: &str = r#"
+local rate, burst, ttl = tonumber(ARGV[1]), tonumber(ARGV[2]), tonumber(ARGV[3])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(bucket[1]) or burst
+local elapsed = math.max(now - (tonumber(bucket[2]) or now), 0) / 1000
+tokens = math.min(tokens + elapsed * rate, burst)
+local wait = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+else
+    wait = math.ceil((1 - tokens) / rate * 1000)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', now)
+redis.call('PEXPIRE', KEYS[1], ttl)
+return wait
+"#;
+
+pub struct RateLimitService;
+
+impl RateLimitService {
+    /// Counts mails against the application's per second, minute & day windows,
+    /// nothing is counted when any of the windows would be exceeded
+    pub fn reserve(
+        &mut self,
+        app: &AppState,
+        application: &Application,
+        count: i64,
+    ) -> AppResult<()> {
+        let windows = [
+            ("second", 1, application.rate_limit_per_second),
+            ("minute", 60, application.rate_limit_per_minute),
+            ("day", 86400, application.rate_limit_per_day),
+        ];
+
+        let now = Utc::now().timestamp();
+        let windows: Vec<(&str, i64, i64)> = windows
+            .into_iter()
+            .filter_map(|(window, seconds, limit)| Some((window, seconds, limit? as i64)))
+            .collect();
+
+        // waiting would not help a request that can never fit in the window
+        if let Some((window, _, limit)) = windows.iter().find(|(_, _, limit)| count > *limit) {
+            return Err(AppMessage::WarningMessage(format!(
+                "a request of {} mails exceeds the rate limit of {} mails per {}",
+                count, limit, window
+            )));
+        }
+
+        if windows.is_empty() {
+            return Ok(());
+        }
+
+        let mut script = redis::cmd("EVAL");
+        script.arg(RESERVE_SCRIPT).arg(windows.len());
+        for (window, seconds, _) in &windows {
+            script.arg(format!(
+                "{}:{}:{}:{}",
+                RATE_LIMIT_KEY_PREFIX,
+                application.application_id,
+                window,
+                now / seconds
+            ));
+        }
+
+        script.arg(count);
+        for (_, seconds, limit) in &windows {
+            script.arg(limit).arg(seconds);
+        }
+
+        let exceeded = script.query::<usize>(&mut app.redis.clone())?;
+        if let Some((window, seconds, limit)) = exceeded.checked_sub(1).map(|i| windows[i]) {
+            return Err(AppMessage::TooManyRequests(
+                format!("rate limit of {} mails per {} exceeded", limit, window),
+                (seconds - now % seconds) as u64,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Takes a token from the application's bucket and from each limited recipient domain,
+    /// then a sending slot of each of those domains, a mail that has to wait for either is put
+    /// back onto the processing queue through the delayed queue instead of being sent
+    pub async fn throttle(
+        &mut self,
+        app: &AppState,
+        saved: &MailSaved,
+    ) -> AppResult<ThrottleDecision> {
+        let application =
+            ApplicationRepository.find_by_id(app.database(), saved.mail.application_id)?;
+        let rate = application
+            .rate_limit_per_second
+            .map(|rate| rate as f64)
+            .unwrap_or(app.send_rate_per_second);
+
+        let key = format!("{}:{}", THROTTLE_KEY_PREFIX, application.application_id);
+        let wait = self.take_token(app, &key, rate).await?;
+        if wait > 0 {
+            return self.defer(app, saved, wait);
+        }
+
+        let domains = self.limited_domains(app, saved);
+        for (domain, limit) in &domains {
+            let key = format!("{}:domain:{}", THROTTLE_KEY_PREFIX, domain);
+            let wait = self.take_token(app, &key, limit.rate_per_second).await?;
+            if wait > 0 {
+                return self.defer(app, saved, wait);
+            }
+        }
+
+        let mail_id = saved.mail.mail_id;
+        let mut acquired = vec![];
+        for (domain, limit) in domains {
+            match self
+                .acquire_slot(app, mail_id, &domain, limit.concurrency)
+                .await
+            {
+                Ok(true) => acquired.push(domain),
+                Ok(false) => {
+                    self.release(app, mail_id, &acquired);
+                    return self.defer(app, saved, DOMAIN_BUSY_DELAY_MILLIS);
+                }
+                Err(err) => {
+                    self.release(app, mail_id, &acquired);
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(ThrottleDecision::Permitted(acquired))
+    }
+
+    /// Frees the sending slots taken by `throttle` once the mail has been handled
+    pub fn release(&mut self, app: &AppState, mail_id: Uuid, domains: &[String]) {
+        let mut redis = app.redis.clone();
+        for domain in domains {
+            let key = format!("{}:{}", SENDING_KEY_PREFIX, domain);
+            if let Err(err) = redis.zrem::<&str, String, i32>(&key, mail_id.to_string()) {
+                error!(
+                    "[throttle] failed to release sending slot of {}: {:?}",
+                    domain, err
+                );
+            }
+        }
+    }
+
+    fn defer(
+        &mut self,
+        app: &AppState,
+        saved: &MailSaved,
+        wait: u64,
+    ) -> AppResult<ThrottleDecision> {
+        let due = current_timestamp() + Duration::milliseconds(wait as i64);
+        let queue = MailService.processing_queue(app, &saved.mail);
+        MailService.push_to_delayed_queue(app, queue, saved, due)?;
+        Ok(ThrottleDecision::Deferred)
+    }
+
+    /// Recipient domains of the mail that are subject to a limit, along with that limit
+    fn limited_domains(&mut self, app: &AppState, saved: &MailSaved) -> Vec<(String, DomainLimit)> {
+        let mut domains: Vec<String> = saved
+            .receiver
+            .iter()
+            .chain(saved.cc.iter())
+            .chain(saved.bcc.iter())
+            .filter_map(|mailbox| mailbox.email.rsplit_once('@'))
+            .map(|(_, domain)| domain.to_lowercase())
+            .collect();
+        domains.sort();
+        domains.dedup();
+
+        domains
+            .into_iter()
+            .filter_map(|domain| {
+                let limit = app
+                    .domain_limits
+                    .get(&domain)
+                    .or_else(|| app.domain_limits.get("*"))?
+                    .clone();
+                Some((domain, limit))
+            })
+            .collect()
+    }
+
+    /// Records the mail among those being sent to the domain, unless the domain is at capacity,
+    /// slots are held per mail so that those of a worker that died mid-send expire on their own
+    async fn acquire_slot(
+        &mut self,
+        app: &AppState,
+        mail_id: Uuid,
+        domain: &str,
+        concurrency: i64,
+    ) -> AppResult<bool> {
+        let key = format!("{}:{}", SENDING_KEY_PREFIX, domain);
+        let ttl = app.smtp_pool.timeout * 3;
+
+        app.services
+            .redis_next
+            .eval(
+                ACQUIRE_SLOT_SCRIPT,
+                &[&key],
+                (mail_id.to_string(), concurrency, ttl),
+            )
+            .await
+    }
+
+    /// Token bucket holding up to a second worth of tokens,
+    /// returns how many milliseconds to wait for the next token when the bucket is empty
+    async fn take_token(&mut self, app: &AppState, key: &str, rate: f64) -> AppResult<u64> {
+        let burst = rate.max(1.0);
+        let ttl = (burst / rate * 1000.0) as i64 + 1000;
+
+        app.services
+            .redis_next
+            .eval(TAKE_TOKEN_SCRIPT, &[key], (rate, burst, ttl))
+            .await
+    }
+}
@@ -2,7 +2,7 @@ use std::num::NonZeroUsize;
 
 use log::{debug, error};
 use mobc::Connection;
-use redis::{AsyncCommands, FromRedisValue};
+use redis::{AsyncCommands, Direction, FromRedisValue, ToRedisArgs};
 use serde::Serialize;
 
 use crate::enums::app_message::AppMessage;
@@ -100,6 +100,26 @@ impl RedisNextService {
         }
     }
 
+    /// Runs a Lua script, which redis executes atomically
+    pub async fn eval<T: FromRedisValue, A: ToRedisArgs>(
+        &self,
+        script: &str,
+        keys: &[&str],
+        args: A,
+    ) -> AppResult<T> {
+        match self.pool.get().await {
+            Ok(mut conn) => redis::cmd("EVAL")
+                .arg(script)
+                .arg(keys.len())
+                .arg(keys)
+                .arg(args)
+                .query_async::<T>(&mut *conn)
+                .await
+                .into_app_result(),
+            Err(err) => Err(AppMessage::RedisPoolError(err)),
+        }
+    }
+
     pub async fn rpop(&self, key: &str, count: Option<NonZeroUsize>) -> AppResult<Option<String>> {
         match self.pool.get().await {
             Ok(mut conn) => conn
@@ -109,4 +129,56 @@ impl RedisNextService {
             Err(err) => Err(AppMessage::RedisPoolError(err)),
         }
     }
+
+    pub async fn lmove(
+        &self,
+        source: &str,
+        destination: &str,
+        src_dir: Direction,
+        dst_dir: Direction,
+    ) -> AppResult<Option<String>> {
+        match self.pool.get().await {
+            Ok(mut conn) => conn
+                .lmove::<&str, &str, Option<String>>(source, destination, src_dir, dst_dir)
+                .await
+                .into_app_result(),
+            Err(err) => Err(AppMessage::RedisPoolError(err)),
+        }
+    }
+
+    /// Blocks the pooled connection for up to `timeout` seconds until an item can be popped
+    pub async fn blpop(&self, key: &str, timeout: f64) -> AppResult<Option<String>> {
+        match self.pool.get().await {
+            Ok(mut conn) => conn
+                .blpop::<&str, Option<[String; 2]>>(key, timeout)
+                .await
+                .map(|popped| popped.map(|[_, item]| item))
+                .into_app_result(),
+            Err(err) => Err(AppMessage::RedisPoolError(err)),
+        }
+    }
+
+    /// Blocks the pooled connection for up to `timeout` seconds until an item can be moved
+    pub async fn blmove(
+        &self,
+        source: &str,
+        destination: &str,
+        src_dir: Direction,
+        dst_dir: Direction,
+        timeout: f64,
+    ) -> AppResult<Option<String>> {
+        match self.pool.get().await {
+            Ok(mut conn) => conn
+                .blmove::<&str, &str, Option<String>>(
+                    source,
+                    destination,
+                    src_dir,
+                    dst_dir,
+                    timeout,
+                )
+                .await
+                .into_app_result(),
+            Err(err) => Err(AppMessage::RedisPoolError(err)),
+        }
+    }
 }
@@ -1,7 +1,7 @@
 use crate::results::redis_result::ToLocalRedisResult;
 use crate::results::RedisResult;
 use log::{debug, error};
-use redis::{Client, Commands, FromRedisValue};
+use redis::{Client, Commands, ExistenceCheck, FromRedisValue, SetExpiry, SetOptions};
 use serde::Serialize;
 
 #[derive(Clone)]
@@ -50,6 +50,39 @@ impl RedisService {
             .set::<String, String, String>(key, serde_json::to_string(&value).unwrap())
     }
 
+    pub fn set_ex<T: Serialize>(
+        &mut self,
+        key: String,
+        value: T,
+        seconds: u64,
+    ) -> redis::RedisResult<String> {
+        self.redis.set_ex::<String, String, String>(
+            key,
+            serde_json::to_string(&value).unwrap(),
+            seconds,
+        )
+    }
+
+    /// Same as `set_ex`, but only when the key does not exist yet, returns whether it was set
+    pub fn set_nx_ex<T: Serialize>(
+        &mut self,
+        key: String,
+        value: T,
+        seconds: u64,
+    ) -> redis::RedisResult<bool> {
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(seconds));
+
+        self.redis
+            .set_options::<String, String, Option<String>>(
+                key,
+                serde_json::to_string(&value).unwrap(),
+                options,
+            )
+            .map(|reply| reply.is_some())
+    }
+
     pub fn get<T: FromRedisValue>(&mut self, key: String) -> redis::RedisResult<T> {
         self.redis.get::<String, T>(key)
     }
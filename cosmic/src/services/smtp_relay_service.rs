@@ -0,0 +1,282 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use diesel::SaveChangesDsl;
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::transport::smtp::PoolConfig;
+use lettre::{AsyncSmtpTransport, Tokio1Executor};
+use rand::Rng;
+use uuid::Uuid;
+
+use crate::app_state::{AppState, SmtpPoolConfig, SmtpRelayTransport};
+use crate::enums::app_message::AppMessage;
+use crate::enums::auth_permission::AuthPermission;
+use crate::helpers::auth::verify_auth_permission;
+use crate::helpers::crypto::{decrypt, encrypt};
+use crate::helpers::db::DatabaseConnectionHelper;
+use crate::helpers::http::QueryParams;
+use crate::helpers::time::current_timestamp;
+use crate::helpers::DBPool;
+use crate::models::smtp_relay::{
+    ApplicationSmtpRelay, SmtpRelay, SmtpRelayCreateForm, SmtpRelayEncryption, SmtpRelayUpdateForm,
+};
+use crate::repositories::smtp_relay_repository::SmtpRelayRepository;
+use crate::results::app_result::FormatAppResult;
+use crate::results::{AppPaginationResult, AppResult};
+
+const PASSWORD_PURPOSE: &str = "smtp-relay-password";
+
+pub struct SmtpRelayService;
+
+impl SmtpRelayService {
+    pub fn create(
+        &mut self,
+        app: &AppState,
+        created_by: Uuid,
+        mut form: SmtpRelayCreateForm,
+    ) -> AppResult<SmtpRelay> {
+        Self::verify_encryption(&form.encryption)?;
+        form.password = form.password.map(|password| Self::encrypt(app, &password));
+        SmtpRelayRepository.create(app.database(), created_by, form)
+    }
+
+    pub fn update(
+        &mut self,
+        app: &AppState,
+        id: Uuid,
+        user_id: Uuid,
+        form: SmtpRelayUpdateForm,
+    ) -> AppResult<SmtpRelay> {
+        Self::verify_encryption(&form.encryption)?;
+
+        let pool = app.database();
+        let mut relay = self.find_accessible(pool, id, user_id)?;
+        relay.name = form.name;
+        relay.host = form.host;
+        relay.port = form.port;
+        relay.encryption = form.encryption;
+        relay.username = form.username;
+        relay.password = match form.password {
+            Some(password) => Some(Self::encrypt(app, &password)),
+            None => relay.password,
+        };
+        relay.priority = form.priority.unwrap_or(relay.priority);
+        relay.weight = form.weight.unwrap_or(relay.weight);
+        relay.is_active = form.is_active.unwrap_or(relay.is_active);
+        relay.updated_at = current_timestamp();
+        relay
+            .save_changes::<SmtpRelay>(&mut pool.conn())
+            .into_app_result()
+    }
+
+    /// Administrators see every relay, other users only the ones they created
+    pub fn list(
+        &mut self,
+        pool: &DBPool,
+        user_id: Uuid,
+        q: QueryParams,
+    ) -> AppPaginationResult<SmtpRelay> {
+        let created_by = match Self::manages_all(pool, user_id) {
+            true => None,
+            false => Some(user_id),
+        };
+
+        SmtpRelayRepository.list(pool, created_by, q)
+    }
+
+    pub fn delete(&mut self, pool: &DBPool, id: Uuid, user_id: Uuid) -> AppResult<SmtpRelay> {
+        self.find_accessible(pool, id, user_id)?;
+        SmtpRelayRepository.delete(pool, id)
+    }
+
+    /// Relays are only accessible to the user that created them, and to administrators
+    pub fn find_accessible(
+        &mut self,
+        pool: &DBPool,
+        id: Uuid,
+        user_id: Uuid,
+    ) -> AppResult<SmtpRelay> {
+        let relay = SmtpRelayRepository.find_by_id(pool, id)?;
+        if relay.created_by != user_id && !Self::manages_all(pool, user_id) {
+            return Err(AppMessage::EntityNotFound(String::from("smtp relay")));
+        }
+
+        Ok(relay)
+    }
+
+    pub fn assign(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        created_by: Uuid,
+        mut ids: Vec<Uuid>,
+    ) -> AppResult<Vec<ApplicationSmtpRelay>> {
+        ids.sort();
+        ids.dedup();
+        for id in &ids {
+            self.find_accessible(pool, *id, created_by)?;
+        }
+
+        SmtpRelayRepository.assign(pool, app_id, created_by, ids)
+    }
+
+    /// Transports to attempt, in order, when sending on behalf of the application,
+    /// the default transport is used when the application has no active relay
+    pub fn transports(
+        &mut self,
+        app: &AppState,
+        app_id: Uuid,
+    ) -> AppResult<Vec<(String, AsyncSmtpTransport<Tokio1Executor>)>> {
+        let relays = SmtpRelayRepository.list_active_by_application(app.database(), app_id)?;
+        if relays.is_empty() {
+            return Ok(vec![(String::from("default"), app.smtp.clone())]);
+        }
+
+        let mut transports = vec![];
+        for relay in self.order(relays) {
+            let transport = self.transport(app, &relay)?;
+            transports.push((relay.name, transport));
+        }
+
+        Ok(transports)
+    }
+
+    /// Sorts by priority, relays sharing a priority are shuffled according to their weight
+    fn order(&mut self, mut relays: Vec<SmtpRelay>) -> Vec<SmtpRelay> {
+        relays.sort_by_key(|relay| relay.priority);
+
+        let mut ordered = vec![];
+        while !relays.is_empty() {
+            let priority = relays[0].priority;
+            let end = relays
+                .iter()
+                .position(|relay| relay.priority != priority)
+                .unwrap_or(relays.len());
+            let mut group: Vec<SmtpRelay> = relays.drain(..end).collect();
+
+            while !group.is_empty() {
+                let total: i64 = group.iter().map(|r| r.weight.max(1) as i64).sum();
+                let mut pick = rand::rng().random_range(0..total);
+                let index = group
+                    .iter()
+                    .position(|relay| {
+                        pick -= relay.weight.max(1) as i64;
+                        pick < 0
+                    })
+                    .unwrap_or(0);
+                ordered.push(group.remove(index));
+            }
+        }
+
+        ordered
+    }
+
+    fn transport(
+        &mut self,
+        app: &AppState,
+        relay: &SmtpRelay,
+    ) -> AppResult<AsyncSmtpTransport<Tokio1Executor>> {
+        let mut cache = app.smtp_relays.lock().unwrap();
+        if let Some(cached) = cache.get(&relay.smtp_relay_id)
+            && cached.updated_at == relay.updated_at
+        {
+            return Ok(cached.transport.clone());
+        }
+
+        let password = match &relay.password {
+            Some(password) => {
+                decrypt(password, &app.app_key, PASSWORD_PURPOSE).ok_or_else(|| {
+                    AppMessage::WarningMessage(format!(
+                        "password of relay {} cannot be decrypted, it has to be set again",
+                        relay.name
+                    ))
+                })?
+            }
+            None => String::new(),
+        };
+
+        let credentials = relay
+            .username
+            .clone()
+            .map(|username| Credentials::new(username, password));
+
+        let transport = Self::build_transport(
+            &relay.host,
+            relay.port as u16,
+            &relay.encryption,
+            credentials,
+            &app.smtp_pool,
+        )?;
+
+        cache.insert(
+            relay.smtp_relay_id,
+            SmtpRelayTransport {
+                updated_at: relay.updated_at,
+                transport: transport.clone(),
+            },
+        );
+
+        Ok(transport)
+    }
+
+    pub fn build_transport(
+        host: &str,
+        port: u16,
+        encryption: &str,
+        credentials: Option<Credentials>,
+        pool: &SmtpPoolConfig,
+    ) -> AppResult<AsyncSmtpTransport<Tokio1Executor>> {
+        let mut builder = match Self::verify_encryption(encryption)? {
+            SmtpRelayEncryption::Local => {
+                return Ok(Self::configure(
+                    AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host).port(port),
+                    pool,
+                ));
+            }
+            SmtpRelayEncryption::Basic => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host).port(port)
+            }
+            SmtpRelayEncryption::Startls | SmtpRelayEncryption::Tls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                    .map_err(|err| AppMessage::WarningMessage(err.to_string()))?
+                    .port(port)
+                    .authentication(vec![Mechanism::Login])
+            }
+        };
+
+        if let Some(credentials) = credentials {
+            builder = builder.credentials(credentials);
+        }
+
+        Ok(Self::configure(builder, pool))
+    }
+
+    fn configure(
+        builder: lettre::transport::smtp::AsyncSmtpTransportBuilder,
+        pool: &SmtpPoolConfig,
+    ) -> AsyncSmtpTransport<Tokio1Executor> {
+        builder
+            .timeout(Some(Duration::from_secs(pool.timeout)))
+            .pool_config(
+                PoolConfig::new()
+                    .max_size(pool.size)
+                    .idle_timeout(Duration::from_secs(pool.idle_timeout)),
+            )
+            .build()
+    }
+
+    /// Passwords are stored encrypted with a key derived from `MAILER_APP_KEY`
+    fn encrypt(app: &AppState, password: &str) -> String {
+        encrypt(password, &app.app_key, PASSWORD_PURPOSE)
+    }
+
+    fn manages_all(pool: &DBPool, user_id: Uuid) -> bool {
+        verify_auth_permission(pool, user_id, AuthPermission::SmtpRelayManageAll).is_ok()
+    }
+
+    fn verify_encryption(encryption: &str) -> AppResult<SmtpRelayEncryption> {
+        SmtpRelayEncryption::from_str(encryption).map_err(|_| {
+            AppMessage::WarningMessageStr("Encryption must be one of (local, basic, startls, tls)")
+        })
+    }
+}
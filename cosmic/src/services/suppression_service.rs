@@ -0,0 +1,172 @@
+use std::str::FromStr;
+
+use diesel::SaveChangesDsl;
+use uuid::Uuid;
+
+use crate::enums::app_message::AppMessage;
+use crate::enums::auth_permission::AuthPermission;
+use crate::helpers::auth::verify_auth_permission;
+use crate::helpers::db::DatabaseConnectionHelper;
+use crate::helpers::DBPool;
+use crate::models::mail::{MailBox, MailSaved};
+use crate::models::mail_error::SmtpErrorDetail;
+use crate::models::suppression::{
+    SuppressedRecipient, Suppression, SuppressionCreateForm, SuppressionReason, SuppressionSource,
+    SuppressionUpdateForm,
+};
+use crate::repositories::application_repository::ApplicationRepository;
+use crate::repositories::suppression_repository::SuppressionRepository;
+use crate::results::app_result::FormatAppResult;
+use crate::results::AppResult;
+
+pub struct SuppressionService;
+
+impl SuppressionService {
+    pub fn create(
+        &mut self,
+        pool: &DBPool,
+        created_by: Uuid,
+        form: SuppressionCreateForm,
+    ) -> AppResult<Suppression> {
+        Self::verify_reason(&form.reason)?;
+        self.verify_access(pool, form.application_id, created_by)?;
+        SuppressionRepository
+            .create(pool, Some(created_by), SuppressionSource::Api, form)?
+            .ok_or(AppMessage::WarningMessageStr(
+                "address is already suppressed",
+            ))
+    }
+
+    pub fn update(
+        &mut self,
+        pool: &DBPool,
+        id: Uuid,
+        user_id: Uuid,
+        form: SuppressionUpdateForm,
+    ) -> AppResult<Suppression> {
+        Self::verify_reason(&form.reason)?;
+
+        let mut suppression = self.find_accessible(pool, id, user_id)?;
+        suppression.reason = form.reason;
+        suppression.description = form.description;
+        suppression
+            .save_changes::<Suppression>(&mut pool.conn())
+            .into_app_result()
+    }
+
+    pub fn delete(&mut self, pool: &DBPool, id: Uuid, user_id: Uuid) -> AppResult<Suppression> {
+        self.find_accessible(pool, id, user_id)?;
+        SuppressionRepository.delete(pool, id)
+    }
+
+    pub fn find_accessible(
+        &mut self,
+        pool: &DBPool,
+        id: Uuid,
+        user_id: Uuid,
+    ) -> AppResult<Suppression> {
+        let suppression = SuppressionRepository.find_by_id(pool, id)?;
+        self.verify_access(pool, suppression.application_id, user_id)?;
+        Ok(suppression)
+    }
+
+    /// Suppressions of an application are only accessible to its owner,
+    /// global ones apply to every application and are left to administrators
+    pub fn verify_access(
+        &mut self,
+        pool: &DBPool,
+        app_id: Option<Uuid>,
+        user_id: Uuid,
+    ) -> AppResult<()> {
+        match app_id {
+            Some(app_id) => {
+                ApplicationRepository.find_owned_by_id(pool, app_id, user_id)?;
+                Ok(())
+            }
+            None => verify_auth_permission(pool, user_id, AuthPermission::SuppressionManageGlobal),
+        }
+    }
+
+    /// Removes suppressed addresses from the recipient lists, returning the removed ones
+    pub fn drop_suppressed(
+        &mut self,
+        pool: &DBPool,
+        app_id: Uuid,
+        lists: [&mut Vec<MailBox>; 3],
+    ) -> AppResult<Vec<SuppressedRecipient>> {
+        let emails = lists
+            .iter()
+            .flat_map(|list| list.iter().map(|mailbox| mailbox.email.clone()))
+            .collect();
+
+        let suppressions = SuppressionRepository.find_by_emails(pool, app_id, emails)?;
+        if suppressions.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut dropped = vec![];
+        for list in lists {
+            list.retain(|mailbox| {
+                let email = mailbox.email.to_lowercase();
+                match suppressions.iter().find(|s| s.email == email) {
+                    Some(suppression) => {
+                        dropped.push(SuppressedRecipient {
+                            email: mailbox.email.clone(),
+                            reason: suppression.reason.clone(),
+                        });
+                        false
+                    }
+                    None => true,
+                }
+            });
+        }
+
+        Ok(dropped)
+    }
+
+    /// Suppresses the recipient of a mail permanently rejected because of its address (`5.1.x`),
+    /// mails addressing several recipients are left alone as the rejected one is unknown
+    pub fn suppress_rejected(
+        &mut self,
+        pool: &DBPool,
+        saved: &MailSaved,
+        detail: &SmtpErrorDetail,
+        error: &str,
+    ) -> AppResult<Option<Suppression>> {
+        let address_rejected = match &detail.enhanced_code {
+            Some(code) => code.starts_with("5.1."),
+            None => matches!(detail.smtp_code, Some(550 | 551 | 553)),
+        };
+
+        let recipients: Vec<&MailBox> = saved
+            .receiver
+            .iter()
+            .chain(saved.cc.iter())
+            .chain(saved.bcc.iter())
+            .collect();
+
+        if !detail.permanent || !address_rejected || recipients.len() != 1 {
+            return Ok(None);
+        }
+
+        SuppressionRepository.create(
+            pool,
+            None,
+            SuppressionSource::Smtp,
+            SuppressionCreateForm {
+                application_id: Some(saved.mail.application_id),
+                email: recipients[0].email.clone(),
+                reason: SuppressionReason::HardBounce.to_string(),
+                description: Some(error.to_string()),
+            },
+        )
+    }
+
+    fn verify_reason(reason: &str) -> AppResult<SuppressionReason> {
+        SuppressionReason::from_str(reason).map_err(|_| {
+            AppMessage::WarningMessageStr(
+                "Reason must be one of (hard_bounce, complaint, unsubscribe, manual)",
+            )
+        })
+    }
+}
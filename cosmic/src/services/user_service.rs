@@ -14,7 +14,7 @@ use crate::helpers::id_generator::number_generator;
 use crate::helpers::string::{password_hash, string};
 use crate::helpers::time::current_timestamp;
 use crate::helpers::DBPool;
-use crate::models::mail::MailBox;
+use crate::models::mail::{MailBox, MailPriority};
 use crate::models::user::{
     FullName, User, UserCacheData, UserCacheable, UserRegisterForm, UserSharableData, UserStatus,
     UserUpdateForm, UsernameAvailability,
@@ -216,10 +216,18 @@ impl UserService {
         context.insert("code", &code);
         context.insert("link", &link);
 
+        let fallback = format!(
+            "Hi {}, your email verification code is {}, or verify using the link below\n{}",
+            user.full_name(),
+            code,
+            link
+        );
+
         MailerService::new(app.clone())
+            .priority(MailPriority::High)
             .subject(app.title("Email Verification"))
             .receivers(vec![MailBox::new(&user.full_name(), user.email.as_str())])
-            .view("account-confirmation", context)
+            .view_or("account-confirmation", context, fallback)
             .send_silently();
     }
 
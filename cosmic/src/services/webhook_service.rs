@@ -0,0 +1,199 @@
+use chrono::{Duration, Utc};
+use diesel::SaveChangesDsl;
+use log::error;
+use reqwest::header;
+use reqwest::Client;
+
+use crate::app_state::AppState;
+use crate::enums::app_message::AppMessage;
+use crate::helpers::db::DatabaseConnectionHelper;
+use crate::helpers::hmac::hmac_sign_timestamped;
+use crate::helpers::time::current_timestamp;
+use crate::models::mail::{Mail, MailCallbackPayload};
+use crate::models::webhook_delivery::{
+    MailWebhookEvent, WebhookDelivery, WebhookDeliveryStatus, WebhookEvent,
+};
+use crate::repositories::app_key_repository::AppKeyRepository;
+use crate::repositories::application_repository::ApplicationRepository;
+use crate::repositories::webhook_delivery_repository::WebhookDeliveryRepository;
+use crate::results::app_result::FormatAppResult;
+use crate::results::{AppResult, RedisResult};
+use crate::services::mail_service::MailService;
+
+/// maximum number of response characters kept in the delivery log
+const RESPONSE_BODY_MAX_LENGTH: usize = 2000;
+
+/// longest wait between two attempts, however large the backoff grows
+const RETRY_DELAY_MAX_SECONDS: i64 = 86_400;
+
+pub struct WebhookService;
+
+impl WebhookService {
+    pub fn dispatch(
+        &mut self,
+        app: &AppState,
+        mail: &Mail,
+        event: WebhookEvent,
+        error: Option<String>,
+    ) -> RedisResult<i32> {
+        MailService.push_to_callback_queue(
+            app,
+            MailCallbackPayload {
+                application_id: mail.application_id,
+                webhook_delivery_id: None,
+                event: MailWebhookEvent {
+                    event: event.to_string(),
+                    mail_id: mail.mail_id,
+                    reference: mail.reference.clone(),
+                    status: mail.status.clone(),
+                    trials: mail.trials,
+                    error,
+                    occurred_at: current_timestamp(),
+                },
+            },
+        )
+    }
+
+    /// Posts the event to the application's webhook url and records the attempt,
+    /// returns None when the application has no webhook configured
+    pub async fn deliver(
+        &mut self,
+        app: &AppState,
+        client: &Client,
+        payload: &MailCallbackPayload,
+    ) -> AppResult<Option<WebhookDelivery>> {
+        let pool = app.database();
+        let application = ApplicationRepository.find_by_id(pool, payload.application_id)?;
+        let url = application.webhook.trim().to_string();
+        if url.is_empty() {
+            return Ok(None);
+        }
+
+        let mut delivery = match payload.webhook_delivery_id {
+            Some(id) => WebhookDeliveryRepository.find_by_id(pool, id)?,
+            None => WebhookDeliveryRepository.create(
+                pool,
+                application.application_id,
+                payload.event.mail_id,
+                payload.event.event.clone(),
+                url,
+                serde_json::to_string(&payload.event)?,
+            )?,
+        };
+
+        // unsigned events could not be told apart from forged ones, they are never sent
+        let key = match AppKeyRepository.find_active_by_app_id(pool, application.application_id) {
+            Ok(key) => Some(key),
+            Err(AppMessage::EntityNotFound(_)) => None,
+            Err(err) => return Err(err),
+        };
+
+        delivery.attempts += 1;
+        let delivered = match key {
+            Some(key) => {
+                let timestamp = Utc::now().timestamp();
+                let signature =
+                    hmac_sign_timestamped(&delivery.payload, &key.private_key, timestamp);
+                let response = client
+                    .post(delivery.url.clone())
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header("X-Mailer-Event", delivery.event.clone())
+                    .header(
+                        "X-Mailer-Delivery",
+                        delivery.webhook_delivery_id.to_string(),
+                    )
+                    .header("X-Mailer-Signature", signature)
+                    .body(delivery.payload.clone())
+                    .send()
+                    .await;
+
+                match response {
+                    Ok(response) => {
+                        let status = response.status();
+                        let body = response.text().await.unwrap_or_default();
+                        delivery.response_status = Some(status.as_u16() as i16);
+                        delivery.response_body =
+                            Some(body.chars().take(RESPONSE_BODY_MAX_LENGTH).collect());
+                        status.is_success()
+                    }
+                    Err(err) => {
+                        delivery.response_status = None;
+                        delivery.response_body = Some(err.to_string());
+                        false
+                    }
+                }
+            }
+            None => {
+                error!(
+                    "[webhook] application #{} has no active key to sign events with",
+                    application.application_id
+                );
+                delivery.response_status = None;
+                delivery.response_body = Some(String::from(
+                    "not sent: the application has no active key to sign the event with",
+                ));
+                false
+            }
+        };
+
+        match delivered {
+            true => {
+                delivery.status = WebhookDeliveryStatus::Delivered.to_string();
+                delivery.delivered_at = Some(current_timestamp());
+                delivery.next_attempt_at = None;
+            }
+            false if delivery.attempts < app.webhook_max_retrials => {
+                let delay = Self::retry_delay(app.webhook_backoff, delivery.attempts);
+                delivery.status = WebhookDeliveryStatus::Retrying.to_string();
+                delivery.next_attempt_at = Some(current_timestamp() + Duration::seconds(delay));
+            }
+            false => {
+                delivery.status = WebhookDeliveryStatus::Failed.to_string();
+                delivery.next_attempt_at = None;
+            }
+        }
+
+        delivery
+            .save_changes::<WebhookDelivery>(&mut pool.conn())
+            .into_app_result()
+            .map(Some)
+    }
+
+    /// Exponential backoff (in seconds) after the given number of attempts
+    fn retry_delay(backoff: i64, attempts: i16) -> i64 {
+        let exponent = (attempts.max(1) - 1).min(16) as u32;
+        backoff
+            .saturating_mul(2_i64.pow(exponent))
+            .clamp(0, RETRY_DELAY_MAX_SECONDS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doubles_the_retry_delay_per_attempt() {
+        let delays: Vec<i64> = (1..=4)
+            .map(|attempts| WebhookService::retry_delay(30, attempts))
+            .collect();
+        assert_eq!(delays, vec![30, 60, 120, 240]);
+        assert_eq!(WebhookService::retry_delay(30, 0), 30);
+    }
+
+    #[test]
+    fn caps_the_retry_delay() {
+        assert_eq!(
+            WebhookService::retry_delay(30, i16::MAX),
+            RETRY_DELAY_MAX_SECONDS
+        );
+        assert_eq!(
+            WebhookService::retry_delay(i64::MAX, 20),
+            RETRY_DELAY_MAX_SECONDS
+        );
+        assert_eq!(WebhookService::retry_delay(-30, 3), 0);
+
+        // the largest delay can be added to a timestamp
+        let _ = current_timestamp() + Duration::seconds(WebhookService::retry_delay(i64::MAX, 20));
+    }
+}
@@ -0,0 +1,2 @@
+ALTER TABLE mails
+    ADD COLUMN reference VARCHAR(250) NULL DEFAULT NULL;
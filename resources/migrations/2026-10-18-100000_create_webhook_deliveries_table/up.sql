@@ -0,0 +1,25 @@
+CREATE TABLE webhook_deliveries
+(
+    webhook_delivery_id UUID          NOT NULL UNIQUE PRIMARY KEY,
+    application_id      UUID          NOT NULL,
+    mail_id             UUID          NOT NULL,
+    event               VARCHAR(50)   NOT NULL,
+    url                 VARCHAR(1000) NOT NULL,
+    payload             TEXT          NOT NULL,
+    attempts            SMALLINT      NOT NULL DEFAULT 0,
+    response_status     SMALLINT      NULL     DEFAULT NULL,
+    response_body       TEXT          NULL     DEFAULT NULL,
+    status              VARCHAR(50)   NOT NULL DEFAULT 'pending',
+    next_attempt_at     TIMESTAMP     NULL     DEFAULT NULL,
+    delivered_at        TIMESTAMP     NULL     DEFAULT NULL,
+    created_at          TIMESTAMP     NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at          TIMESTAMP     NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+SELECT auto_handle_updated_at('webhook_deliveries');
+
+ALTER TABLE webhook_deliveries
+    ADD CONSTRAINT fk_webhook_deliveries_application_id FOREIGN KEY (application_id) REFERENCES applications (application_id);
+
+ALTER TABLE webhook_deliveries
+    ADD CONSTRAINT fk_webhook_deliveries_mail_id FOREIGN KEY (mail_id) REFERENCES mails (mail_id);
@@ -0,0 +1,4 @@
+ALTER TABLE mail_errors
+    DROP COLUMN smtp_code,
+    DROP COLUMN enhanced_code,
+    DROP COLUMN is_permanent;
\ No newline at end of file
@@ -0,0 +1,4 @@
+ALTER TABLE mail_errors
+    ADD COLUMN smtp_code     SMALLINT    NULL     DEFAULT NULL,
+    ADD COLUMN enhanced_code VARCHAR(20) NULL     DEFAULT NULL,
+    ADD COLUMN is_permanent  BOOLEAN     NOT NULL DEFAULT FALSE;
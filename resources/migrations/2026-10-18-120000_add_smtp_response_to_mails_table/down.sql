@@ -0,0 +1,5 @@
+ALTER TABLE mails
+    DROP COLUMN smtp_response_code,
+    DROP COLUMN smtp_response,
+    DROP COLUMN smtp_queue_id,
+    DROP COLUMN message_id;
\ No newline at end of file
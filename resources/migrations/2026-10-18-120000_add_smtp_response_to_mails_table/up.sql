@@ -0,0 +1,5 @@
+ALTER TABLE mails
+    ADD COLUMN smtp_response_code SMALLINT     NULL DEFAULT NULL,
+    ADD COLUMN smtp_response      TEXT         NULL DEFAULT NULL,
+    ADD COLUMN smtp_queue_id      VARCHAR(250) NULL DEFAULT NULL,
+    ADD COLUMN message_id         VARCHAR(250) NULL DEFAULT NULL;
@@ -0,0 +1 @@
+DROP TABLE mail_attachments;
\ No newline at end of file
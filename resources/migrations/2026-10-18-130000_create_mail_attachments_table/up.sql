@@ -0,0 +1,17 @@
+CREATE TABLE mail_attachments
+(
+    mail_attachment_id UUID         NOT NULL UNIQUE PRIMARY KEY,
+    mail_id            UUID         NOT NULL,
+    file_upload_id     UUID         NULL     DEFAULT NULL,
+    file_name          VARCHAR(255) NOT NULL,
+    content_type       VARCHAR(150) NOT NULL,
+    size               INTEGER      NOT NULL,
+    content            BYTEA        NOT NULL,
+    created_at         TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE mail_attachments
+    ADD CONSTRAINT fk_mail_attachments_mail_id FOREIGN KEY (mail_id) REFERENCES mails (mail_id);
+
+ALTER TABLE mail_attachments
+    ADD CONSTRAINT fk_mail_attachments_file_upload_id FOREIGN KEY (file_upload_id) REFERENCES file_uploads (file_upload_id);
@@ -0,0 +1,2 @@
+ALTER TABLE applications
+    DROP COLUMN max_attachment_size;
\ No newline at end of file
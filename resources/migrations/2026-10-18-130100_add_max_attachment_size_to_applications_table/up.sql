@@ -0,0 +1,2 @@
+ALTER TABLE applications
+    ADD COLUMN max_attachment_size BIGINT NOT NULL DEFAULT 10485760;
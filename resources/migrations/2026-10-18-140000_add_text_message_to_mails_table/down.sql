@@ -0,0 +1,2 @@
+ALTER TABLE mails
+    DROP COLUMN text_message;
\ No newline at end of file
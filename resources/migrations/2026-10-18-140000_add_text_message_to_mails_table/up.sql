@@ -0,0 +1,2 @@
+ALTER TABLE mails
+    ADD COLUMN text_message TEXT NULL DEFAULT NULL;
@@ -0,0 +1,24 @@
+CREATE TABLE mail_templates
+(
+    mail_template_id UUID         NOT NULL UNIQUE PRIMARY KEY,
+    application_id   UUID         NOT NULL,
+    created_by       UUID         NOT NULL,
+    name             VARCHAR(150) NOT NULL,
+    version          INTEGER      NOT NULL DEFAULT 1,
+    subject          VARCHAR(250) NOT NULL,
+    html             TEXT         NOT NULL,
+    text             TEXT         NULL     DEFAULT NULL,
+    is_latest        BOOLEAN      NOT NULL DEFAULT TRUE,
+    created_at       TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at       TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    deleted_at       TIMESTAMP    NULL     DEFAULT NULL,
+    UNIQUE (application_id, name, version)
+);
+
+SELECT auto_handle_updated_at('mail_templates');
+
+ALTER TABLE mail_templates
+    ADD CONSTRAINT fk_mail_templates_application_id FOREIGN KEY (application_id) REFERENCES applications (application_id);
+
+ALTER TABLE mail_templates
+    ADD CONSTRAINT fk_mail_templates_created_by FOREIGN KEY (created_by) REFERENCES users (user_id);
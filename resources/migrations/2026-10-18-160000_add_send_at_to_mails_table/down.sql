@@ -0,0 +1,2 @@
+ALTER TABLE mails
+    DROP COLUMN send_at;
\ No newline at end of file
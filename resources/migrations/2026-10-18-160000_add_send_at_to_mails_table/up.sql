@@ -0,0 +1,2 @@
+ALTER TABLE mails
+    ADD COLUMN send_at TIMESTAMP NULL DEFAULT NULL;
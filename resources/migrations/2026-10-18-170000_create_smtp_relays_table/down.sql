@@ -0,0 +1,3 @@
+DROP TABLE application_smtp_relays;
+
+DROP TABLE smtp_relays;
\ No newline at end of file
@@ -0,0 +1,41 @@
+CREATE TABLE smtp_relays
+(
+    smtp_relay_id UUID         NOT NULL UNIQUE PRIMARY KEY,
+    created_by    UUID         NOT NULL,
+    name          VARCHAR(150) NOT NULL,
+    host          VARCHAR(250) NOT NULL,
+    port          INTEGER      NOT NULL,
+    encryption    VARCHAR(50)  NOT NULL,
+    username      VARCHAR(250) NULL     DEFAULT NULL,
+    password      TEXT         NULL     DEFAULT NULL,
+    priority      SMALLINT     NOT NULL DEFAULT 0,
+    weight        SMALLINT     NOT NULL DEFAULT 1,
+    is_active     BOOLEAN      NOT NULL DEFAULT TRUE,
+    created_at    TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at    TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    deleted_at    TIMESTAMP    NULL     DEFAULT NULL
+);
+
+SELECT auto_handle_updated_at('smtp_relays');
+
+ALTER TABLE smtp_relays
+    ADD CONSTRAINT fk_smtp_relays_created_by FOREIGN KEY (created_by) REFERENCES users (user_id);
+
+CREATE TABLE application_smtp_relays
+(
+    application_smtp_relay_id UUID      NOT NULL UNIQUE PRIMARY KEY,
+    application_id            UUID      NOT NULL,
+    smtp_relay_id             UUID      NOT NULL,
+    created_by                UUID      NOT NULL,
+    created_at                TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (application_id, smtp_relay_id)
+);
+
+ALTER TABLE application_smtp_relays
+    ADD CONSTRAINT fk_application_smtp_relays_application_id FOREIGN KEY (application_id) REFERENCES applications (application_id);
+
+ALTER TABLE application_smtp_relays
+    ADD CONSTRAINT fk_application_smtp_relays_smtp_relay_id FOREIGN KEY (smtp_relay_id) REFERENCES smtp_relays (smtp_relay_id);
+
+ALTER TABLE application_smtp_relays
+    ADD CONSTRAINT fk_application_smtp_relays_created_by FOREIGN KEY (created_by) REFERENCES users (user_id);
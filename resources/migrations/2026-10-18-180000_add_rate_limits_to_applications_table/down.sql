@@ -0,0 +1,5 @@
+ALTER TABLE applications
+    DROP COLUMN rate_limit_per_second,
+    DROP COLUMN rate_limit_per_minute,
+    DROP COLUMN rate_limit_per_day,
+    DROP COLUMN max_recipients;
\ No newline at end of file
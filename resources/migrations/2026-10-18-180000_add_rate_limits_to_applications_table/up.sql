@@ -0,0 +1,5 @@
+ALTER TABLE applications
+    ADD COLUMN rate_limit_per_second INT NULL DEFAULT NULL,
+    ADD COLUMN rate_limit_per_minute INT NULL DEFAULT NULL,
+    ADD COLUMN rate_limit_per_day    INT NULL DEFAULT NULL,
+    ADD COLUMN max_recipients        INT NULL DEFAULT NULL;
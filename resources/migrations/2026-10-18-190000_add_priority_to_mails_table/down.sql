@@ -0,0 +1,2 @@
+ALTER TABLE mails
+    DROP COLUMN priority;
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE mails
+    ADD COLUMN priority VARCHAR(20) NOT NULL DEFAULT 'normal';
@@ -0,0 +1,4 @@
+ALTER TABLE mails
+    DROP COLUMN suppressed_recipients;
+
+DROP TABLE suppressions;
\ No newline at end of file
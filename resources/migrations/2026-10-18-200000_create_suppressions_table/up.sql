@@ -0,0 +1,27 @@
+CREATE TABLE suppressions
+(
+    suppression_id UUID         NOT NULL UNIQUE PRIMARY KEY,
+    application_id UUID         NULL     DEFAULT NULL,
+    created_by     UUID         NULL     DEFAULT NULL,
+    email          VARCHAR(250) NOT NULL,
+    reason         VARCHAR(50)  NOT NULL,
+    source         VARCHAR(50)  NOT NULL,
+    description    TEXT         NULL     DEFAULT NULL,
+    created_at     TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at     TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+SELECT auto_handle_updated_at('suppressions');
+
+-- a null application_id suppresses the address for every application
+CREATE UNIQUE INDEX suppressions_application_id_email_unique
+    ON suppressions (COALESCE(application_id, '00000000-0000-0000-0000-000000000000'), email);
+
+ALTER TABLE suppressions
+    ADD CONSTRAINT fk_suppressions_application_id FOREIGN KEY (application_id) REFERENCES applications (application_id);
+
+ALTER TABLE suppressions
+    ADD CONSTRAINT fk_suppressions_created_by FOREIGN KEY (created_by) REFERENCES users (user_id);
+
+ALTER TABLE mails
+    ADD COLUMN suppressed_recipients JSONB NULL DEFAULT NULL;